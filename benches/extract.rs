@@ -0,0 +1,85 @@
+//! Compares naive parallel extraction, one rayon task per file through a
+//! shared reader, against `extract_adaptive` on an archive mixing thousands
+//! of small files of varying size with a few medium and one large file. Run
+//! with `cargo bench --features rayon --bench extract`.
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+use zarchive::{reader::ZArchiveReader, ZArchiveWriter};
+
+const SMALL_FILES: usize = 4000;
+/// Small files range from 512 bytes up to this size.
+const SMALL_MAX: usize = 256 * 1024;
+const MEDIUM_FILES: usize = 8;
+const MEDIUM_SIZE: usize = 8 * 1024 * 1024;
+const LARGE_SIZE: usize = 128 * 1024 * 1024;
+const RUNS: u32 = 5;
+
+fn data(len: usize, seed: u64) -> Vec<u8> {
+    // text-like data which zstd compresses to about half its size
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            b"etaoin shrdlu\n"[(state % 14) as usize]
+        })
+        .collect()
+}
+
+fn time(runs: u32, mut f: impl FnMut()) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn main() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("mixed.zar");
+    let mut writer = ZArchiveWriter::new(&path).unwrap();
+    for i in 0..SMALL_FILES {
+        writer
+            .start_file(&format!("small/{}/{i}.txt", i % 64))
+            .unwrap();
+        let size = 512 << (i % 10);
+        writer
+            .append_data(&data(size.min(SMALL_MAX), i as u64))
+            .unwrap();
+    }
+    for i in 0..MEDIUM_FILES {
+        writer.start_file(&format!("medium/{i}.bin")).unwrap();
+        writer
+            .append_data(&data(MEDIUM_SIZE, (SMALL_FILES + i) as u64))
+            .unwrap();
+    }
+    writer.start_file("large.bin").unwrap();
+    writer.append_data(&data(LARGE_SIZE, 0)).unwrap();
+    writer.finalize().unwrap();
+
+    let archive = ZArchiveReader::open(&path).unwrap();
+    let files = archive.get_files().unwrap();
+    let naive = time(RUNS, || {
+        let dest = tempfile::tempdir().unwrap();
+        files
+            .par_iter()
+            .try_for_each(|file| archive.extract_file_into(file, dest.path()))
+            .unwrap();
+    });
+    let adaptive = time(RUNS, || {
+        let dest = tempfile::tempdir().unwrap();
+        archive.extract_adaptive(dest.path()).unwrap();
+    });
+    println!(
+        "{SMALL_FILES} x 0.5-{} KiB + {MEDIUM_FILES} x {} MiB + 1 x {} MiB on {} threads, best of {RUNS}",
+        SMALL_MAX / 1024,
+        MEDIUM_SIZE / 1024 / 1024,
+        LARGE_SIZE / 1024 / 1024,
+        rayon::current_num_threads()
+    );
+    println!("naive parallel extract: {naive:?}");
+    println!("extract_adaptive:       {adaptive:?}");
+}
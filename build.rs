@@ -6,7 +6,7 @@ fn main() {
     println!("cargo:rerun-if-changed=include/zarchive/zarchivereader.h");
     println!("cargo:rerun-if-changed=include/zarchive/zarchivewriter.h");
     println!("cargo:rustc-link-lib=static=zstd");
-    cxx_build::bridges(["src/reader.rs", "src/writer.rs"].into_iter())
+    cxx_build::bridges(["src/reader.rs", "src/writer.rs"])
         .file("include/sha_256.c")
         .include("include")
         .flag("-w")
@@ -3,6 +3,7 @@
 #include <string>
 #include <string_view>
 #include <cstring>
+#include <filesystem>
 
 /* Determine endianness */
 /* Original code by https://github.com/rofl0r */
@@ -381,5 +382,15 @@ namespace _ZARCHIVE
 		return 0;
 	}
 
+	// builds a path from UTF-8 so that non-ASCII and extended-length (\\?\) paths survive the conversion on Windows
+	inline std::filesystem::path PathFromUtf8(const char* data, size_t size)
+	{
+#if defined(__cpp_char8_t)
+		return std::filesystem::path(std::u8string(reinterpret_cast<const char8_t*>(data), size));
+#else
+		return std::filesystem::u8path(data, data + size);
+#endif
+	}
+
 };
 
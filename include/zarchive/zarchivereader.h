@@ -5,6 +5,10 @@
 #include <string_view>
 #include <unordered_map>
 #include <mutex>
+#include <chrono>
+#include <string>
+#include <functional>
+#include <optional>
 
 #include <filesystem>
 #include <fstream>
@@ -13,18 +17,26 @@
 
 using ZArchiveNodeHandle = uint32_t;
 struct DirEntry;
+struct ReadSource;
+enum class ReadErrorKind : uint8_t;
 
 static inline ZArchiveNodeHandle ZARCHIVE_INVALID_NODE = 0xFFFFFFFF;
 
 class ZArchiveReader
 {
 public:
-	static std::unique_ptr<ZArchiveReader> OpenFromFile(const std::filesystem::path& path);
+	// baseOffset and windowSize select the part of the file holding the archive, e.g. when it is embedded in a larger file
+	static std::unique_ptr<ZArchiveReader> OpenFromFile(const std::filesystem::path& path, uint64_t baseOffset = 0, uint64_t windowSize = UINT64_MAX);
+	// the reader takes ownership of the buffer and serves all reads from it
+	static std::unique_ptr<ZArchiveReader> OpenFromMemory(rust::Vec<uint8_t>&& data);
+	// all reads go through the source, which must hold an archive of the given size
+	static std::unique_ptr<ZArchiveReader> OpenFromSource(rust::Box<ReadSource>&& source, uint64_t archiveSize);
 
 	ZArchiveReader(std::ifstream&& file, std::vector<_ZARCHIVE::CompressionOffsetRecord>&& offsetRecords, std::vector<uint8_t>&& nameTable, std::vector<_ZARCHIVE::FileDirectoryEntry>&& fileTree, uint64_t compressedDataOffset, uint64_t compressedDataSize);
 	~ZArchiveReader();
 
 	ZArchiveNodeHandle LookUp(rust::Str path, bool allowFile = true, bool allowDirectory = true);
+	ZArchiveNodeHandle LookUpSegments(rust::Slice<const rust::Str> segments);
 	bool IsDirectory(ZArchiveNodeHandle nodeHandle) const;
 	bool IsFile(ZArchiveNodeHandle nodeHandle) const;
 
@@ -35,6 +47,33 @@ public:
 	// file operations
 	uint64_t GetFileSize(ZArchiveNodeHandle nodeHandle);
 	uint64_t ReadFromFile(ZArchiveNodeHandle nodeHandle, uint64_t offset, uint64_t length, uint8_t* buffer);
+	// what kind of failure made the last ReadFromFile call throw
+	ReadErrorKind GetLastErrorKind() const;
+	uint64_t GetFileDataOffset(ZArchiveNodeHandle nodeHandle) const;
+
+	// block layout
+	uint64_t GetBlockPhysicalOffset(uint64_t blockIndex) const;
+	uint32_t GetBlockCompressedSize(uint64_t blockIndex) const;
+
+	// archive info
+	uint32_t GetVersion() const; // format version number, decoded from the footer
+	uint64_t GetBlockCount() const;
+	uint64_t GetOffsetRecordCount() const;
+	uint64_t GetCompressedDataSize() const;
+	uint64_t GetFileCount() const;
+
+	// raw archive access
+	uint64_t GetArchiveSize();
+	uint64_t ReadArchiveBytes(uint64_t offset, uint64_t length, uint8_t* buffer);
+
+	// when disabled, the archive file is closed after each operation and reopened on demand
+	void SetKeepOpen(bool keepOpen);
+
+	// opens the archive again from the same path, e.g. after it was rewritten on disk
+	std::unique_ptr<ZArchiveReader> Reopen();
+
+	// failed reads from the archive file are retried this many times, reopening the file and waiting backoffUs microseconds (doubling each time) in between
+	void SetRetryPolicy(uint32_t attempts, uint64_t backoffUs);
 
 private:
 	struct CacheBlock
@@ -59,12 +98,31 @@ private:
 	CacheBlock* RecycleLRUBlock(uint64_t newBlockIndex);
 	void MarkBlockAsMRU(CacheBlock* block);
 
+	static std::unique_ptr<ZArchiveReader> Load(uint64_t fileSize, const std::function<bool(uint64_t offset, void* buffer, uint32_t size)>& readBytes);
+
+	uint32_t FindChild(uint32_t directoryNode, std::string_view name);
+
 	void RegisterBlock(CacheBlock* block, uint64_t blockIndex);
 	void UnregisterBlock(CacheBlock* block);
 	bool LoadBlock(CacheBlock* block);
 
+	uint64_t GetWindowSize();
+	bool EnsureFileOpen();
+	bool ReadWithRetry(uint64_t offset, void* buffer, uint32_t size);
+	void ReleaseFile();
+
 	static std::string_view GetName(const std::vector<uint8_t>& nameTable, uint32_t nameOffset);
 
+	std::filesystem::path m_path;
+	uint64_t m_baseOffset = 0;
+	uint64_t m_windowSize = UINT64_MAX;
+	bool m_inMemory = false;
+	rust::Vec<uint8_t> m_memory;
+	std::optional<rust::Box<ReadSource>> m_source;
+	uint64_t m_sourceSize = 0;
+	bool m_keepOpen = true;
+	uint32_t m_retryAttempts = 0;
+	std::chrono::microseconds m_retryBackoff{ 0 };
 	std::ifstream m_file;
 	std::vector<_ZARCHIVE::CompressionOffsetRecord> m_offsetRecords;
 	std::vector<uint8_t> m_nameTable;
@@ -72,8 +130,15 @@ private:
 	uint64_t m_compressedDataOffset;
 	uint64_t m_compressedDataSize;
 	uint64_t m_blockCount;
+	uint32_t m_version = _ZARCHIVE::Footer::kVersion1;
+	uint64_t m_fileCount = 0;
 
 	std::vector<uint8_t> m_blockDecompressionBuffer;
+	std::string m_lastError; // reason the last block failed to load
+	ReadErrorKind m_lastErrorKind{};
 };
 
 std::unique_ptr<ZArchiveReader> OpenFromFile(const rust::Str path);
+std::unique_ptr<ZArchiveReader> OpenFromMemory(rust::Vec<uint8_t> data);
+std::unique_ptr<ZArchiveReader> OpenFromSource(rust::Box<ReadSource> source, uint64_t size);
+std::unique_ptr<ZArchiveReader> OpenFromFileRange(const rust::Str path, uint64_t offset, uint64_t size);
@@ -4,6 +4,9 @@
 #include <vector>
 #include <string_view>
 #include <unordered_map>
+#include <filesystem>
+#include <fstream>
+#include <memory>
 
 #include "zarchivecommon.h"
 #include "rust/cxx.h";
@@ -38,6 +41,10 @@ public:
 	void AppendData(const void* data, size_t size); // appends data to currently active file
 	bool MakeDir(const char* path, bool recursive = false);
 	void Finalize();
+	void Reset(); // starts a new archive after Finalize, keeping the compression settings and buffer capacity
+
+	void SetCompressionLevel(int32_t level); // zstd level used for blocks stored from now on
+	void SetCompressionEnabled(bool enabled); // when disabled, blocks stored from now on are written uncompressed
 
 private:
 	PathNode* GetNodeByPath(PathNode* root, std::string_view path);
@@ -56,6 +63,8 @@ private:
 	void WriteMetaData();
 	void WriteFooter();
 
+	static void FreeSubnodes(PathNode* node);
+
 private:
 	// callbacks
 	CB_NewOutputFile m_cbNewOutputFile;
@@ -72,6 +81,8 @@ private:
 	// writes and compression
 	std::vector<uint8_t> m_currentWriteBuffer;
 	std::vector<uint8_t> m_compressionBuffer;
+	int32_t m_compressionLevel{ 6 };
+	bool m_compressionEnabled{ true };
 	uint64_t m_currentCompressedWriteIndex{ 0 }; // output file write index
 	uint64_t m_currentInputOffset{ 0 }; // current offset within uncompressed file data
 	// uncompressed-to-compressed offset records
@@ -82,4 +93,29 @@ private:
 	uint8_t m_integritySha[32];
 };
 
+// wraps ZArchiveWriter with a file output and error reporting suitable for the Rust bridge
+class StreamingWriter
+{
+public:
+	StreamingWriter(const std::filesystem::path& outputPath);
+
+	void StartNewFile(rust::Str path);
+	void AppendData(rust::Slice<const uint8_t> data);
+	void MakeDir(rust::Str path, bool recursive);
+	void SetCompressionLevel(int32_t level);
+	void SetCompressionEnabled(bool enabled);
+	void Finalize();
+	void Reset(rust::Str outputPath);
+
+private:
+	static void NewOutputFile(const int32_t partIndex, void* ctx);
+	static void WriteOutputData(const void* data, size_t length, void* ctx);
+
+	std::filesystem::path m_outputPath;
+	std::ofstream m_outputFile;
+	// must be declared last, its constructor opens the output file
+	ZArchiveWriter m_writer;
+};
+
 void Pack(rust::Str inputPath, rust::Str outputPath);
+std::unique_ptr<StreamingWriter> NewStreamingWriter(rust::Str outputPath);
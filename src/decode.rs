@@ -0,0 +1,69 @@
+//! Decoders for entries that carry their own inner compression.
+use crate::Result;
+
+/// Decodes the complete contents of an entry.
+pub type Decoder = fn(&[u8]) -> Result<Vec<u8>>;
+
+/// A set of decoders keyed by the magic bytes at the start of the data they
+/// handle, e.g. `b"Yaz0"` for `.szs` files. See
+/// [`ZArchiveReader::read_file_decoded`](crate::reader::ZArchiveReader::read_file_decoded).
+#[derive(Debug, Default, Clone)]
+pub struct DecoderRegistry {
+    decoders: Vec<(Vec<u8>, Decoder)>,
+}
+
+impl DecoderRegistry {
+    /// Create an empty registry, which copies all data unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a decoder for data starting with `magic`. If more than one
+    /// registered magic matches, the longest wins.
+    pub fn register(&mut self, magic: impl Into<Vec<u8>>, decoder: Decoder) -> &mut Self {
+        self.decoders.push((magic.into(), decoder));
+        self
+    }
+
+    /// Find the decoder for some data by its magic, if one is registered.
+    pub fn find(&self, data: &[u8]) -> Option<Decoder> {
+        self.decoders
+            .iter()
+            .filter(|(magic, _)| data.starts_with(magic))
+            .max_by_key(|(magic, _)| magic.len())
+            .map(|(_, decoder)| *decoder)
+    }
+
+    /// Decode data with the matching decoder, or copy it unchanged if no
+    /// registered magic matches.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.find(data) {
+            Some(decoder) => decoder(data),
+            None => Ok(data.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_magic(data: &[u8]) -> Result<Vec<u8>> {
+        Ok(data[4..].to_vec())
+    }
+
+    fn empty(_: &[u8]) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    #[test]
+    fn decode() {
+        let mut decoders = DecoderRegistry::new();
+        decoders
+            .register(*b"Yaz0", strip_magic)
+            .register(*b"Yaz", empty);
+        assert_eq!(decoders.decode(b"Yaz0data").unwrap(), b"data");
+        assert_eq!(decoders.decode(b"Yaz1data").unwrap(), b"");
+        assert_eq!(decoders.decode(b"SARCdata").unwrap(), b"SARCdata");
+    }
+}
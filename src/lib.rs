@@ -16,9 +16,11 @@
 //!   (not entirely true of the Rust bindings)
 //!
 //! ## Rust Bindings
-//! The `zarchive` crate provides Rust bindings to the C++ library. The API is intentionally
-//! limited. While most of the reader API is implemented, only a basic archive packing function
-//! is exposed for writing, due to complex safety considerations.
+//! The `zarchive` crate provides Rust bindings to the C++ library. Most of the reader API is
+//! implemented. For writing, `pack` packs a directory, and `ZArchiveWriter` builds an archive
+//! file by file (also usable as `std::io::Write`). `pack_stream` and `pack_generated` pack
+//! contents from readers or a callback, `pack_with_progress` reports progress while packing,
+//! `recompress` rewrites an archive, and `ZArchiveWriter::merge` combines several.
 //!
 //! The Rust bindings add some slight overhead to the reader's directory iteration API,
 //! but hopefully with a sufficient benefit of convenience.
@@ -56,13 +58,19 @@
 //! with the exception of [sha_256.c](/src/sha_256.c) and [sha_256.h](/src/sha_256.h)
 //! which are public domain, see:
 //! [https://github.com/amosnier/sha-2]( https://github.com/amosnier/sha-2).
+mod crc;
+mod decode;
+mod nested;
 pub mod reader;
+mod sha;
 mod writer;
 
 use thiserror::Error;
 
-/// The error type for the `zarchive` crate.
+/// The error type for the `zarchive` crate. New variants may be added in
+/// minor releases, so matches need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum ZArchiveError {
     #[error("Invalid file path: {0}")]
     InvalidFilePath(String),
@@ -72,6 +80,27 @@ pub enum ZArchiveError {
     InvalidDestination(String),
     #[error("File not in archive: {0}")]
     MissingFile(String),
+    #[error("Archive entry is a directory, not a file: {0}")]
+    NotAFile(String),
+    #[error("Range {start}..{end} out of bounds for {path} ({size} bytes)")]
+    OutOfBounds {
+        path: String,
+        start: u64,
+        end: u64,
+        size: u64,
+    },
+    #[error("Short read: expected {expected} bytes but got {actual}")]
+    ShortRead { expected: u64, actual: u64 },
+    #[error("{path} is {size} bytes, more than the limit of {limit}")]
+    FileTooLarge { path: String, size: u64, limit: u64 },
+    #[error("Duplicate entry {name} in directory {parent}")]
+    DuplicateEntry { parent: String, name: String },
+    #[error("Failed to decompress {path}: {detail}")]
+    DecompressionFailed { path: String, detail: String },
+    #[error("Extraction needs {required} bytes but only {available} are available")]
+    ExtractLimitExceeded { required: u64, available: u64 },
+    #[error("File present in more than one source archive: {0}")]
+    Conflict(String),
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
     #[error("{0}")]
@@ -79,6 +108,20 @@ pub enum ZArchiveError {
 }
 type Result<T> = std::result::Result<T, ZArchiveError>;
 
+impl From<ZArchiveError> for std::io::Error {
+    fn from(err: ZArchiveError) -> Self {
+        use std::io::ErrorKind;
+        match err {
+            ZArchiveError::IOError(err) => err,
+            ZArchiveError::MissingFile(_) => std::io::Error::new(ErrorKind::NotFound, err),
+            ZArchiveError::InvalidDestination(_)
+            | ZArchiveError::NotADirectory(_)
+            | ZArchiveError::NotAFile(_) => std::io::Error::new(ErrorKind::InvalidInput, err),
+            _ => std::io::Error::other(err),
+        }
+    }
+}
+
 /// Convenience function for extracting a whole archive to a directory.
 pub fn extract(
     archive_path: impl AsRef<std::path::Path>,
@@ -88,5 +131,30 @@ pub fn extract(
     archive.extract(dest)
 }
 
+pub use decode::{Decoder, DecoderRegistry};
+pub use nested::{NestedOpener, NestedOpeners, NestedWalk};
 /// Pack a directory into an archive.
 pub use writer::pack;
+pub use writer::{
+    pack_generated, pack_stream, pack_with_progress, recompress, CompressPredicate, ConflictPolicy,
+    PackOptions, PackProgress, PackSummary, ZArchiveWriter,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::ZArchiveError;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn io_error() {
+        let err: std::io::Error = ZArchiveError::MissingFile("a.txt".to_owned()).into();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let err: std::io::Error = ZArchiveError::NotADirectory("a.txt".to_owned()).into();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err: std::io::Error =
+            ZArchiveError::IOError(std::io::Error::from(ErrorKind::PermissionDenied)).into();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        let err: std::io::Error = ZArchiveError::Conflict("a.txt".to_owned()).into();
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+}
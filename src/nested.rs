@@ -0,0 +1,214 @@
+//! Openers for entries which are themselves containers of files.
+use crate::{
+    reader::{WalkWithDepth, ZArchiveReader},
+    Result,
+};
+
+/// Lists the files in a container, given its complete contents, as pairs of
+/// path within the container and file contents.
+pub type NestedOpener = fn(&[u8]) -> Result<Vec<(String, Vec<u8>)>>;
+
+/// A set of openers for container formats, recognized by the extension of
+/// the entry's name or the magic bytes at the start of its data. See
+/// [`ZArchiveReader::walk_nested`].
+#[derive(Debug, Default, Clone)]
+pub struct NestedOpeners {
+    extensions: Vec<(String, NestedOpener)>,
+    magics: Vec<(Vec<u8>, NestedOpener)>,
+}
+
+impl NestedOpeners {
+    /// Create an empty set, which opens no entries as containers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an opener for entries whose names end in `.ext`, compared
+    /// case-insensitively. Extensions are checked before magics.
+    pub fn register_extension(&mut self, ext: &str, opener: NestedOpener) -> &mut Self {
+        self.extensions.push((ext.to_ascii_lowercase(), opener));
+        self
+    }
+
+    /// Register an opener for data starting with `magic`. If more than one
+    /// registered magic matches, the longest wins.
+    pub fn register(&mut self, magic: impl Into<Vec<u8>>, opener: NestedOpener) -> &mut Self {
+        self.magics.push((magic.into(), opener));
+        self
+    }
+
+    fn by_extension(&self, name: &str) -> Option<NestedOpener> {
+        let (_, ext) = name.rsplit_once('.')?;
+        self.extensions
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+            .map(|(_, opener)| *opener)
+    }
+
+    fn by_magic(&self, data: &[u8]) -> Option<NestedOpener> {
+        self.magics
+            .iter()
+            .filter(|(magic, _)| data.starts_with(magic))
+            .max_by_key(|(magic, _)| magic.len())
+            .map(|(_, opener)| *opener)
+    }
+
+    /// Find the opener for an entry by its name or the start of its data, if
+    /// one is registered.
+    pub fn find(&self, name: &str, data: &[u8]) -> Option<NestedOpener> {
+        self.by_extension(name).or_else(|| self.by_magic(data))
+    }
+
+    fn magic_len(&self) -> usize {
+        self.magics
+            .iter()
+            .map(|(magic, _)| magic.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Depth-first iterator over every file in an archive and in the containers
+/// nested inside it. See [`ZArchiveReader::walk_nested`].
+#[derive(Debug)]
+pub struct NestedWalk<'a> {
+    reader: &'a ZArchiveReader,
+    openers: &'a NestedOpeners,
+    walk: WalkWithDepth<'a>,
+    nested: Vec<OpenContainer>,
+}
+
+/// The path of an open container along with its unvisited files.
+type OpenContainer = (String, std::vec::IntoIter<(String, Vec<u8>)>);
+
+impl<'a> NestedWalk<'a> {
+    pub(crate) fn new(reader: &'a ZArchiveReader, openers: &'a NestedOpeners) -> Result<Self> {
+        Ok(NestedWalk {
+            reader,
+            openers,
+            walk: reader.walk_with_depth()?,
+            nested: vec![],
+        })
+    }
+
+    fn next_nested(&mut self) -> Option<Result<(String, u64)>> {
+        loop {
+            let (container, files) = self.nested.last_mut()?;
+            let Some((name, data)) = files.next() else {
+                self.nested.pop();
+                continue;
+            };
+            let path = format!("{container}!/{name}");
+            if let Some(opener) = self.openers.find(&name, &data) {
+                match opener(&data) {
+                    Ok(files) => self.nested.push((path.clone(), files.into_iter())),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            return Some(Ok((path, data.len() as u64)));
+        }
+    }
+
+    fn next_outer(&mut self) -> Option<Result<(String, u64)>> {
+        let entry = match self.walk.by_ref().find(|entry| match entry {
+            Ok((_, entry)) => entry.is_file(),
+            Err(_) => true,
+        })? {
+            Ok((_, entry)) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let path = entry.full_path().to_owned();
+        let size = entry.size().unwrap_or_default() as u64;
+        let opener = match self.openers.by_extension(entry.name()) {
+            Some(opener) => Some(opener),
+            None => {
+                match self
+                    .reader
+                    .read_from_file_clamped(&path, 0, self.openers.magic_len() as u64)
+                {
+                    Ok(head) => self.openers.by_magic(&head),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        };
+        if let Some(opener) = opener {
+            let files = self
+                .reader
+                .try_read_from_file(&path, 0, size)
+                .and_then(|data| opener(&data));
+            match files {
+                Ok(files) => self.nested.push((path.clone(), files.into_iter())),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok((path, size)))
+    }
+}
+
+impl<'a> Iterator for NestedWalk<'a> {
+    type Item = Result<(String, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_nested().or_else(|| self.next_outer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZArchiveWriter;
+
+    // A toy container: the magic followed by `name=contents` lines.
+    fn open_pak(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+        Ok(std::str::from_utf8(&data[4..])
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let (name, contents) = line.split_once('=').unwrap();
+                (name.to_owned(), contents.as_bytes().to_vec())
+            })
+            .collect())
+    }
+
+    #[test]
+    fn walk_nested() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested.zar");
+        let mut writer = ZArchiveWriter::new(&path).unwrap();
+        writer.start_file("plain.txt").unwrap();
+        writer.append_data(b"hello").unwrap();
+        writer.start_file("dir/outer.bin").unwrap();
+        writer
+            .append_data(b"PAK1a.txt=abc\ninner.pak=PAK1b.txt=de")
+            .unwrap();
+        writer.finalize().unwrap();
+        let archive = ZArchiveReader::open(&path).unwrap();
+
+        let mut openers = NestedOpeners::new();
+        openers
+            .register(*b"PAK1", open_pak)
+            .register_extension("PAK", open_pak);
+        let mut files: Vec<(String, u64)> = archive
+            .walk_nested(&openers)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                ("dir/outer.bin".to_owned(), 36),
+                ("dir/outer.bin!/a.txt".to_owned(), 3),
+                ("dir/outer.bin!/inner.pak".to_owned(), 12),
+                ("dir/outer.bin!/inner.pak!/b.txt".to_owned(), 2),
+                ("plain.txt".to_owned(), 5),
+            ]
+        );
+        let plain: Vec<_> = archive
+            .walk_nested(&NestedOpeners::new())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(plain.len(), 2);
+    }
+}
@@ -11,10 +11,19 @@
 //!    println!("{}", entry.name());
 //! }
 //! ```
-use crate::{Result, ZArchiveError};
+use crate::{
+    crc, sha, writer::archive_path, DecoderRegistry, NestedOpeners, NestedWalk, Result,
+    ZArchiveError,
+};
 use cxx::{type_id, ExternType};
 use smallvec::{smallvec, SmallVec};
-use std::{io::Write, path::Path, sync::RwLock};
+use std::{
+    cell::RefCell,
+    io::Write,
+    ops::{ControlFlow, Range},
+    path::Path,
+    sync::RwLock,
+};
 
 /// Wraps a handle to a file or directory node in an open archive.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -27,6 +36,114 @@ unsafe impl ExternType for ZArchiveNodeHandle {
     type Kind = cxx::kind::Trivial;
 }
 
+const BLOCK_SIZE: usize = 64 * 1024;
+/// The least a reader opened with [`ZArchiveReader::from_reader`] fetches
+/// from its source at once.
+const READ_AHEAD: usize = 16 * BLOCK_SIZE;
+const MAX_POOLED_BUFFERS: usize = 4;
+/// The size from which [`ZArchiveReader::extract_adaptive`] decompresses a
+/// file's blocks in parallel rather than extracting it alongside others.
+#[cfg(feature = "rayon")]
+const ADAPTIVE_LARGE_FILE: u64 = 16 * BLOCK_SIZE as u64;
+/// How many compressed blocks [`ZArchiveReader::read_file_parallel`] reads
+/// per turn holding the reader's lock.
+#[cfg(feature = "rayon")]
+const PARALLEL_READ_BATCH: usize = 16;
+
+/// Read from a file through the C++ reader. A block which fails to
+/// decompress is reported as [`ZArchiveError::DecompressionFailed`], going by
+/// the kind of failure the reader recorded; anything else is passed through
+/// as-is.
+///
+/// # Safety
+///
+/// `buffer` must be valid for writes of `length` bytes.
+unsafe fn read_from_raw(
+    reader: &mut RawReader,
+    file: &str,
+    handle: ZArchiveNodeHandle,
+    offset: u64,
+    length: u64,
+    buffer: *mut u8,
+) -> Result<u64> {
+    reader
+        .pin_mut()
+        .ReadFromFile(handle, offset, length, buffer)
+        .map_err(|err| match reader.GetLastErrorKind() {
+            ffi::ReadErrorKind::Decompression => ZArchiveError::DecompressionFailed {
+                path: file.to_owned(),
+                detail: err.what().to_owned(),
+            },
+            _ => ZArchiveError::Other(err),
+        })
+}
+
+/// Write a string as a JSON string literal, quotes included.
+fn write_json_string(w: &mut impl Write, s: &str) -> std::io::Result<()> {
+    w.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if (c as u32) < 0x20 => "",
+            _ => continue,
+        };
+        w.write_all(&s.as_bytes()[start..i])?;
+        if escaped.is_empty() {
+            write!(w, "\\u{:04x}", c as u32)?;
+        } else {
+            w.write_all(escaped.as_bytes())?;
+        }
+        start = i + c.len_utf8();
+    }
+    w.write_all(&s.as_bytes()[start..])?;
+    w.write_all(b"\"")
+}
+
+/// Match a single path segment against a glob segment, where `*` matches any
+/// run of characters and `?` matches any one character. Like archive lookups,
+/// the comparison ignores ASCII case.
+fn segment_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            segment_matches(rest, name)
+                || (!name.is_empty() && segment_matches(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => segment_matches(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => {
+            p.eq_ignore_ascii_case(n) && segment_matches(rest, name_rest)
+        }
+        _ => false,
+    }
+}
+
+/// Match split path segments against split glob segments, where a `**`
+/// segment matches any number of path segments, including none.
+fn glob_matches(pattern: &[impl AsRef<str>], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((seg, rest)) if seg.as_ref() == "**" => {
+            (0..=path.len()).any(|i| glob_matches(rest, &path[i..]))
+        }
+        Some((seg, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                segment_matches(seg.as_ref().as_bytes(), name.as_bytes())
+                    && glob_matches(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+thread_local! {
+    static READ_BUFFERS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
 /// Represents an entry when iterating an archive directory, either a file or
 /// subdirectory.
 #[derive(Debug, Clone)]
@@ -53,7 +170,7 @@ impl<'a> DirEntry<'a> {
 
     /// Returns the size of the entry, if it is a file.
     pub fn size(&self) -> Option<usize> {
-        self.inner.isFile.then(|| self.inner.size as usize)
+        self.inner.isFile.then_some(self.inner.size as usize)
     }
 
     /// Returns the full path to the entry.
@@ -152,384 +269,4282 @@ impl<'a> Iterator for ArchiveDirIterator<'a> {
     }
 }
 
-/// Represents an open ZArchive, wrapping the C++ type.  
-///
-/// It holds an open file handle to the archive on disk, which it retains until
-/// destroyed. The archive is read-only, but the C++ struct mutates constantly
-/// for many operations. For this reason, the Rust struct wraps it in an
-/// [`RwLock`](std::sync::RwLock) to provide a simple immutable interface that
-/// works as expected in any context, including mulithreaded.
-pub struct ZArchiveReader(RwLock<cxx::UniquePtr<ffi::ZArchiveReader>>);
+/// An owned version of [`DirEntry`], not tied to the lifetime of the reader.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedDirEntry {
+    name: String,
+    path: String,
+    is_file: bool,
+    size: u64,
+    unexpanded: bool,
+}
 
-impl std::fmt::Debug for ZArchiveReader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("ZArchiveReader")
+impl OwnedDirEntry {
+    fn new(parent: &str, entry: &ffi::DirEntry) -> Self {
+        OwnedDirEntry {
+            name: entry.name.to_owned(),
+            path: if parent.is_empty() {
+                entry.name.to_owned()
+            } else {
+                [parent, entry.name].join("/")
+            },
+            is_file: entry.isFile,
+            size: entry.size,
+            unexpanded: false,
+        }
     }
-}
 
-unsafe impl Send for ZArchiveReader {}
-unsafe impl Sync for ZArchiveReader {}
+    /// Returns the name of the entry.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-impl ZArchiveReader {
-    /// Open a ZArchive from a file.
-    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        Ok(Self(RwLock::new(ffi::OpenFromFile(
-            path.as_ref().to_str().ok_or_else(|| {
-                ZArchiveError::InvalidFilePath(path.as_ref().to_string_lossy().to_string())
-            })?,
-        )?)))
+    /// Returns true if the entry is a file.
+    pub fn is_file(&self) -> bool {
+        self.is_file
     }
 
-    /// Get the size of a file in the archive, if the file exists.
-    pub fn file_size(&self, file: impl AsRef<Path>) -> Option<usize> {
-        let file = file.as_ref().to_str()?;
-        let mut archive = self.0.write().unwrap();
-        let node_handle = archive.pin_mut().LookUp(file, true, false).ok()?;
-        archive
-            .pin_mut()
-            .GetFileSize(node_handle)
-            .ok()
-            .map(|s| s as usize)
+    /// Returns true if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        !self.is_file
     }
 
-    /// Read a file from the archive into a `Vec<u8>`, if the file exists.
-    pub fn read_file(&self, file: impl AsRef<Path>) -> Option<Vec<u8>> {
-        let mut reader = self.0.write().unwrap();
-        let handle = reader
-            .pin_mut()
-            .LookUp(file.as_ref().to_str()?, true, false)
-            .ok()?;
-        if handle == ZARCHIVE_INVALID_NODE {
-            None
-        } else {
-            let size = reader.pin_mut().GetFileSize(handle).ok()?;
-            let mut buffer: Vec<u8> = Vec::with_capacity(size as usize);
-            unsafe {
-                let written = reader
-                    .pin_mut()
-                    .ReadFromFile(handle, 0, size, buffer.as_mut_ptr())
-                    .unwrap();
-                if written != size {
-                    panic!(
-                        "Wrote an unexpected number of bytes, expected {} but got {}",
-                        size, written
-                    );
-                }
-                buffer.set_len(written as usize);
-            };
-            Some(buffer)
-        }
+    /// Returns the size of the entry, if it is a file.
+    pub fn size(&self) -> Option<usize> {
+        self.is_file.then_some(self.size as usize)
     }
 
-    /// Extract a file from the archive to disk, if the file exists. If the destination
-    /// is an existing directory, the file will be extracted into the directory with its
-    /// relative path in the archive. Otherwise it will be extracted to the destination
-    /// path as-is.
-    pub fn extract_file(&self, file: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
-        let file = file.as_ref().to_str().ok_or_else(|| {
-            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
-        })?;
-        let dest = if dest.as_ref().is_dir() {
-            dest.as_ref().join(file)
-        } else {
-            dest.as_ref().to_path_buf()
-        };
-        dest.parent().map(std::fs::create_dir_all).transpose()?;
-        let handle = self
-            .0
-            .write()
-            .unwrap()
-            .pin_mut()
-            .LookUp(file, true, false)?;
-        if handle == ZARCHIVE_INVALID_NODE || !self.0.read().unwrap().IsFile(handle)? {
-            Err(ZArchiveError::MissingFile(file.to_owned()))
-        } else {
-            let mut reader = self.0.write().unwrap();
-            let size = reader.pin_mut().GetFileSize(handle)?;
-            let mut dest_handle = std::fs::File::create(dest)?;
-            dest_handle.set_len(size as u64)?;
-            let mut buffer = vec![0; size as usize];
-            unsafe {
-                let written = reader
-                    .pin_mut()
-                    .ReadFromFile(handle, 0, size, buffer.as_mut_ptr())
-                    .unwrap();
-                if written != size {
-                    panic!(
-                        "Wrote an unexpected number of bytes, expected {} but got {}",
-                        size, written
-                    );
-                }
-                buffer.set_len(written as usize);
-            };
-            std::io::BufWriter::new(&mut dest_handle).write_all(&buffer)?;
-            Ok(())
+    /// Returns the full path to the entry.
+    pub fn full_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns true if the entry is a non-empty directory whose contents were
+    /// not visited because of a depth limit (see
+    /// [`ZArchiveReader::walk_max_depth`]).
+    pub fn has_unexpanded_children(&self) -> bool {
+        self.unexpanded
+    }
+
+    /// Borrow this entry as a [`DirEntry`].
+    fn as_dir_entry(&self) -> DirEntry<'_> {
+        DirEntry {
+            inner: ffi::DirEntry {
+                name: &self.name,
+                isFile: self.is_file,
+                isDirectory: !self.is_file,
+                size: self.size,
+            },
+            parent: match self.path.rsplit_once('/') {
+                Some((parent, _)) => parent.split('/').collect(),
+                None => SmallVec::new(),
+            },
         }
     }
+}
 
-    /// Extract the entire archive to disk.
-    pub fn extract(&self, dest: impl AsRef<Path>) -> Result<()> {
-        let dest = dest.as_ref();
-        if dest.is_file() {
-            Err(ZArchiveError::InvalidDestination(
-                dest.to_string_lossy().to_string(),
-            ))
-        } else {
-            self.get_files().unwrap().into_iter().try_for_each(|file| {
-                let dest = dest.join(&file);
-                if !dest.parent().unwrap().exists() {
-                    std::fs::create_dir_all(dest.parent().unwrap())?;
-                }
-                self.extract_file(&file, &dest)
-            })
+impl From<&DirEntry<'_>> for OwnedDirEntry {
+    fn from(entry: &DirEntry<'_>) -> Self {
+        OwnedDirEntry {
+            name: entry.name().to_owned(),
+            path: entry.full_path(),
+            is_file: entry.is_file(),
+            size: entry.inner.size,
+            unexpanded: false,
         }
     }
+}
 
-    /// Read part of a file from the archive into a `Vec<u8>` using the specified
-    /// length and offet, if the file exists.
-    pub fn read_from_file(
-        &self,
-        file: impl AsRef<Path>,
-        offset: usize,
-        length: usize,
-    ) -> Option<Vec<u8>> {
-        let mut reader = self.0.write().unwrap();
-        let handle = reader
-            .pin_mut()
-            .LookUp(file.as_ref().to_str()?, true, false)
-            .ok()?;
-        if handle == ZARCHIVE_INVALID_NODE {
-            None
-        } else {
-            let size = reader.pin_mut().GetFileSize(handle).ok()?;
-            if length > size as usize {
-                return None;
-            }
-            let mut buffer: Vec<u8> = Vec::with_capacity(length);
-            unsafe {
-                let written = reader
-                    .pin_mut()
-                    .ReadFromFile(handle, offset as u64, length as u64, buffer.as_mut_ptr())
-                    .unwrap();
-                if written != length as u64 {
-                    panic!(
-                        "Wrote an unexpected number of bytes, expected {} but got {}",
-                        length, written
-                    );
-                }
-                buffer.set_len(written as usize);
-            };
-            Some(buffer)
+/// A record of the files written by [`ZArchiveReader::extract_with_manifest`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// A single extracted file in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ManifestEntry {
+    /// The path of the file in the archive, relative to the extraction root.
+    pub path: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The SHA-256 hash of the file contents.
+    pub sha256: [u8; 32],
+}
+
+/// Structural parameters of an archive as stored on disk. See
+/// [`ZArchiveReader::header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveHeader {
+    /// The format version number, decoded from the version word in the
+    /// footer (`0x61bf3a01` for version 1). Only version 1 exists (archives
+    /// with any other version are rejected when opened), and it defines no
+    /// flags.
+    pub version: u32,
+    /// The uncompressed size of each data block. This is fixed by the format
+    /// rather than stored in the archive.
+    pub block_size: u32,
+    /// The number of data blocks addressed by the offset table.
+    pub block_count: u64,
+    /// The number of records in the offset table, each covering 16 blocks.
+    pub offset_record_count: u64,
+    /// The size of the compressed data section in bytes.
+    pub compressed_data_size: u64,
+    /// The number of files in the file tree, counted once when the archive
+    /// is opened.
+    pub file_count: u64,
+}
+
+/// Byte order for the typed read helpers such as
+/// [`ZArchiveReader::read_u32_at`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// Options for [`ZArchiveReader::extract_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractOptions {
+    /// Also create directories which are empty in the archive. Otherwise only
+    /// the directories needed to hold files are created.
+    pub create_empty_dirs: bool,
+    /// Remove the archive's [common prefix](ZArchiveReader::common_prefix)
+    /// from every extracted path, so `content/Actor/x` is extracted to
+    /// `dest/Actor/x` if every file is under `content/`. Empty directories
+    /// outside the prefix keep their full path.
+    pub strip_common_prefix: bool,
+}
+
+/// Options for [`ZArchiveReader::repack_as_zip`]. Requires the `zip`
+/// feature.
+#[cfg(feature = "zip")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZipOptions {
+    /// How each file is stored in the zip.
+    pub compression: zip::CompressionMethod,
+    /// Also add directories which are empty in the archive, which would
+    /// otherwise be left out.
+    pub include_empty_dirs: bool,
+}
+
+#[cfg(feature = "zip")]
+impl Default for ZipOptions {
+    fn default() -> Self {
+        ZipOptions {
+            compression: zip::CompressionMethod::Deflated,
+            include_empty_dirs: false,
         }
     }
+}
 
-    /// Get a list of all the files in the archive (more convenient than manual
-    /// iteration if you can spare the allocation).
-    pub fn get_files(&self) -> Result<Vec<String>> {
-        fn process_dir_entry(
-            archive: &ZArchiveReader,
-            files: &mut Vec<String>,
-            node_handle: ZArchiveNodeHandle,
-            parent: &str,
-            dir_entry: &mut ffi::DirEntry,
-        ) -> Result<()> {
-            let count = archive.0.read().unwrap().GetDirEntryCount(node_handle)?;
-            for i in 0..count {
-                if archive
-                    .0
-                    .read()
-                    .unwrap()
-                    .GetDirEntry(node_handle, i, dir_entry)?
-                {
-                    let full_path = if !parent.is_empty() {
-                        [parent, dir_entry.name].join("/")
-                    } else {
-                        dir_entry.name.to_owned()
+/// How a directory on disk differs from an archive, as reported by
+/// [`ZArchiveReader::dir_changes`]. Paths use the archive's `/` separator and
+/// each list is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirChanges {
+    /// Files on disk that are not in the archive.
+    pub added: Vec<String>,
+    /// Files in the archive that are not on disk.
+    pub deleted: Vec<String>,
+    /// Files in both whose size or SHA-256 hash differs.
+    pub modified: Vec<String>,
+}
+
+/// Progress of [`ZArchiveReader::extract_with_progress`], reported before
+/// each file is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractProgress<'a> {
+    /// The number of files written so far, not including the current one.
+    pub files_done: usize,
+    /// The number of files being extracted in total.
+    pub files_total: usize,
+    /// The number of bytes written so far, not including the current file.
+    pub bytes_done: u64,
+    /// The number of bytes being extracted in total.
+    pub bytes_total: u64,
+    /// The path in the archive of the file about to be written.
+    pub current_file: &'a str,
+}
+
+/// The outcome of [`ZArchiveReader::extract_changed`], listing archive paths.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractReport {
+    /// Files that were missing from the destination.
+    pub added: Vec<String>,
+    /// Files that existed in the destination but differed.
+    pub updated: Vec<String>,
+    /// Files that already matched and were left alone.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug)]
+struct WalkFrame {
+    handle: ZArchiveNodeHandle,
+    index: u32,
+    count: u32,
+    parent: String,
+}
+
+/// Depth-first iterator over every entry in an archive, yielding each entry
+/// along with its depth (0 for top-level entries).
+///
+/// The traversal keeps an explicit stack of open directories rather than
+/// recursing, so arbitrarily deep trees are handled without risk of stack
+/// overflow. If reading the directory tree fails, the error is yielded and
+/// the walk ends.
+#[derive(Debug)]
+pub struct WalkWithDepth<'a> {
+    reader: &'a ZArchiveReader,
+    stack: Vec<WalkFrame>,
+    max_depth: Option<usize>,
+    // Only set when the reader reports events.
+    started: Option<std::time::Instant>,
+    yielded: usize,
+}
+
+impl<'a> WalkWithDepth<'a> {
+    fn new(reader: &'a ZArchiveReader, root: ZArchiveNodeHandle, parent: String) -> Result<Self> {
+        let count = reader.0.read().unwrap().GetDirEntryCount(root)?;
+        Ok(WalkWithDepth {
+            reader,
+            stack: vec![WalkFrame {
+                handle: root,
+                index: 0,
+                count,
+                parent,
+            }],
+            max_depth: None,
+            started: reader.1.as_ref().map(|_| std::time::Instant::now()),
+            yielded: 0,
+        })
+    }
+
+    fn advance(&mut self) -> Result<Option<(usize, OwnedDirEntry)>> {
+        loop {
+            let Some(depth) = self.stack.len().checked_sub(1) else {
+                return Ok(None);
+            };
+            let frame = &mut self.stack[depth];
+            if frame.index >= frame.count {
+                self.stack.pop();
+                continue;
+            }
+            let mut dir_entry = ffi::DirEntry::default();
+            let found = self.reader.0.read().unwrap().GetDirEntry(
+                frame.handle,
+                frame.index,
+                &mut dir_entry,
+            )?;
+            frame.index += 1;
+            if !found {
+                continue;
+            }
+            let mut entry = OwnedDirEntry::new(&frame.parent, &dir_entry);
+            if entry.is_dir() {
+                let mut reader = self.reader.0.write().unwrap();
+                let handle = reader.pin_mut().LookUp(entry.full_path(), false, true)?;
+                if handle != ZARCHIVE_INVALID_NODE {
+                    let count = reader.GetDirEntryCount(handle)?;
+                    let expand = match self.max_depth {
+                        Some(max) => depth < max,
+                        None => true,
                     };
-                    if dir_entry.isFile {
-                        files.push(full_path);
-                    } else if dir_entry.isDirectory {
-                        let next = archive
-                            .0
-                            .write()
-                            .unwrap()
-                            .pin_mut()
-                            .LookUp(&full_path, false, true)?;
-                        if next != ZARCHIVE_INVALID_NODE {
-                            process_dir_entry(archive, files, next, &full_path, dir_entry)?;
-                        }
+                    if expand {
+                        self.stack.push(WalkFrame {
+                            handle,
+                            index: 0,
+                            count,
+                            parent: entry.full_path().to_owned(),
+                        });
+                    } else {
+                        entry.unexpanded = count > 0;
                     }
                 }
             }
-            Ok(())
-        }
-
-        let mut dir_entry = ffi::DirEntry::default();
-        let mut files = vec![];
-        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
-        if root != ZARCHIVE_INVALID_NODE {
-            process_dir_entry(self, &mut files, root, "", &mut dir_entry)?;
+            self.yielded += 1;
+            return Ok(Some((depth, entry)));
         }
-        Ok(files)
     }
+}
 
-    /// Iterate over the contents of the root directory of the archive.
-    pub fn iter(&self) -> Result<ArchiveDirIterator<'_>> {
-        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
-        if root == ZARCHIVE_INVALID_NODE {
-            Err(ZArchiveError::MissingFile("archive root".to_owned()))
-        } else {
-            Ok(ArchiveDirIterator::new(root, smallvec![], self))
+impl Drop for WalkWithDepth<'_> {
+    fn drop(&mut self) {
+        if let (Some(hook), Some(started)) = (&self.reader.1, self.started) {
+            (hook.0)(ReaderEvent::Walk {
+                entries: self.yielded,
+                duration: started.elapsed(),
+            });
         }
     }
+}
 
-    /// Iterate over the contents of a directory in the archive.
-    pub fn iter_dir<'a, 'entry>(
-        &'a self,
-        dir: &'entry DirEntry<'a>,
-    ) -> Result<ArchiveDirIterator<'entry>>
-    where
-        'a: 'entry,
-    {
-        let node_handle =
-            self.0
-                .write()
-                .unwrap()
-                .pin_mut()
-                .LookUp(&dir.full_path(), false, true)?;
-        if node_handle == ZARCHIVE_INVALID_NODE {
-            Err(ZArchiveError::MissingFile(dir.full_path()))
-        } else if !dir.is_dir() {
-            Err(ZArchiveError::NotADirectory(dir.full_path()))
-        } else {
-            Ok(ArchiveDirIterator::new(
-                node_handle,
-                dir.parent
-                    .iter()
-                    .copied()
-                    .chain([dir.name()].into_iter())
-                    .collect(),
-                self,
-            ))
+impl<'a> Iterator for WalkWithDepth<'a> {
+    type Item = Result<(usize, OwnedDirEntry)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.advance() {
+            Ok(entry) => entry.map(Ok),
+            Err(e) => {
+                self.stack.clear();
+                Some(Err(e))
+            }
         }
     }
+}
 
-    /// Count the contents of a directory in the archive.
-    pub fn count_dir_entries<'a>(&'a self, dir: &'a DirEntry) -> Result<usize> {
-        let mut reader = self.0.write().unwrap();
-        let node_handle = reader.pin_mut().LookUp(&dir.full_path(), false, true)?;
-        if node_handle == ZARCHIVE_INVALID_NODE {
-            Err(ZArchiveError::MissingFile(dir.full_path()))
-        } else if !dir.is_dir() {
-            Err(ZArchiveError::NotADirectory(dir.full_path()))
-        } else {
-            Ok(reader.pin_mut().GetDirEntryCount(node_handle)? as usize)
-        }
+/// Lazy depth-first iterator over every entry in an archive. See
+/// [`ZArchiveReader::walk`].
+#[derive(Debug)]
+pub struct WalkIter<'a> {
+    inner: WalkWithDepth<'a>,
+}
+
+impl<'a> Iterator for WalkIter<'a> {
+    type Item = Result<OwnedDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|(_, entry)| entry))
     }
 }
 
-#[cxx::bridge]
-mod ffi {
-    #[derive(Debug, Default, Clone)]
-    #[allow(non_snake_case)]
-    struct DirEntry<'a> {
-        name: &'a str,
-        isFile: bool,
-        isDirectory: bool,
-        size: u64,
+/// Depth-first iterator over every entry in an archive which knows its length
+/// up front. See [`ZArchiveReader::walk_counted`].
+#[derive(Debug)]
+pub struct WalkCounted<'a> {
+    inner: WalkWithDepth<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for WalkCounted<'a> {
+    type Item = Result<OwnedDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.inner.next()?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Some(next.map(|(_, entry)| entry))
     }
 
-    unsafe extern "C++" {
-        include!("zarchive/include/zarchive/zarchivereader.h");
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
 
-        type ZArchiveNodeHandle = super::ZArchiveNodeHandle;
-        type ZArchiveReader;
-        fn OpenFromFile(path: &str) -> Result<UniquePtr<ZArchiveReader>>;
-        fn LookUp(
-            self: Pin<&mut ZArchiveReader>,
-            path: &str,
-            allowFile: bool,
-            allowDirectory: bool,
-        ) -> Result<ZArchiveNodeHandle>;
-        #[allow(unused)]
-        fn IsDirectory(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<bool>;
-        fn IsFile(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<bool>;
-        fn GetDirEntryCount(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<u32>;
-        fn GetDirEntry<'a>(
-            self: &'a ZArchiveReader,
-            nodeHandle: ZArchiveNodeHandle,
-            index: u32,
-            dirEntry: &'a mut DirEntry,
-        ) -> Result<bool>;
-        fn GetFileSize(
-            self: Pin<&mut ZArchiveReader>,
-            nodeHandle: ZArchiveNodeHandle,
-        ) -> Result<u64>;
-        unsafe fn ReadFromFile(
-            self: Pin<&mut ZArchiveReader>,
-            nodeHandle: ZArchiveNodeHandle,
-            offset: u64,
-            size: u64,
-            buffer: *mut u8,
-        ) -> Result<u64>;
+impl ExactSizeIterator for WalkCounted<'_> {}
+
+/// Depth-first iterator over every entry in an archive with the children of
+/// each directory in sorted order. See [`ZArchiveReader::walk_sorted`].
+#[derive(Debug)]
+struct WalkSorted<'a> {
+    reader: &'a ZArchiveReader,
+    // Unvisited children of each open directory, sorted in reverse so the
+    // next entry can be popped off the end.
+    stack: Vec<Vec<OwnedDirEntry>>,
+}
+
+impl<'a> WalkSorted<'a> {
+    fn children(
+        reader: &ZArchiveReader,
+        handle: ZArchiveNodeHandle,
+        parent: &str,
+    ) -> Result<Vec<OwnedDirEntry>> {
+        let reader = reader.0.read().unwrap();
+        let count = reader.GetDirEntryCount(handle)?;
+        let mut dir_entry = ffi::DirEntry::default();
+        let mut children = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            if reader.GetDirEntry(handle, index, &mut dir_entry)? {
+                children.push(OwnedDirEntry::new(parent, &dir_entry));
+            }
+        }
+        children.sort_unstable_by(|a, b| b.name.cmp(&a.name));
+        Ok(children)
+    }
+
+    fn advance(&mut self) -> Result<Option<OwnedDirEntry>> {
+        loop {
+            let Some(frame) = self.stack.last_mut() else {
+                return Ok(None);
+            };
+            let Some(entry) = frame.pop() else {
+                self.stack.pop();
+                continue;
+            };
+            if entry.is_dir() {
+                let handle = self.reader.0.write().unwrap().pin_mut().LookUp(
+                    entry.full_path(),
+                    false,
+                    true,
+                )?;
+                if handle != ZARCHIVE_INVALID_NODE {
+                    let children = Self::children(self.reader, handle, entry.full_path())?;
+                    self.stack.push(children);
+                }
+            }
+            return Ok(Some(entry));
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<'a> Iterator for WalkSorted<'a> {
+    type Item = Result<OwnedDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.advance() {
+            Ok(entry) => entry.map(Ok),
+            Err(e) => {
+                self.stack.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reads several files in an archive one after another as a single stream.
+/// See [`ZArchiveReader::concat_reader`].
+#[derive(Debug)]
+pub struct ConcatReader<'a> {
+    reader: &'a ZArchiveReader,
+    files: Vec<(String, ZArchiveNodeHandle, u64)>,
+    index: usize,
+    offset: u64,
+}
+
+impl std::io::Read for ConcatReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while let Some((file, handle, size)) = self.files.get(self.index) {
+            if self.offset >= *size {
+                self.index += 1;
+                self.offset = 0;
+                continue;
+            }
+            let length = (size - self.offset).min(buf.len() as u64);
+            if length == 0 {
+                return Ok(0);
+            }
+            let written = unsafe {
+                read_from_raw(
+                    &mut self.reader.0.write().unwrap(),
+                    file,
+                    *handle,
+                    self.offset,
+                    length,
+                    buf.as_mut_ptr(),
+                )
+            }?;
+            if written != length {
+                return Err(ZArchiveError::ShortRead {
+                    expected: length,
+                    actual: written,
+                }
+                .into());
+            }
+            self.offset += written;
+            return Ok(written as usize);
+        }
+        Ok(0)
+    }
+}
+
+/// A file in an archive opened for streaming reads and seeking. See
+/// [`ZArchiveReader::open_file`].
+///
+/// Reads smaller than the read-ahead size fetch the aligned chunk around them
+/// into an internal buffer, and following sequential reads are served from it
+/// without another call into the archive. Seeking drops the buffer.
+#[derive(Debug)]
+pub struct ArchiveFile<'a> {
+    reader: &'a ZArchiveReader,
+    path: String,
+    handle: ZArchiveNodeHandle,
+    size: u64,
+    offset: u64,
+    read_ahead: usize,
+    buffer: Vec<u8>,
+    buffer_start: u64,
+}
+
+impl ArchiveFile<'_> {
+    /// Returns the size of the file.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the read-ahead size of the handle.
+    pub fn read_ahead(&self) -> usize {
+        self.read_ahead
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let length = buf.len() as u64;
+        let written = unsafe {
+            read_from_raw(
+                &mut self.reader.0.write().unwrap(),
+                &self.path,
+                self.handle,
+                offset,
+                length,
+                buf.as_mut_ptr(),
+            )
+        }?;
+        if written != length {
+            return Err(ZArchiveError::ShortRead {
+                expected: length,
+                actual: written,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl std::io::Read for ArchiveFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let length = self.size.saturating_sub(self.offset).min(buf.len() as u64) as usize;
+        if length == 0 {
+            return Ok(0);
+        }
+        let buffer_end = self.buffer_start + self.buffer.len() as u64;
+        if !(self.buffer_start..buffer_end).contains(&self.offset) {
+            if length >= self.read_ahead {
+                self.read_exact_at(self.offset, &mut buf[..length])?;
+                self.offset += length as u64;
+                return Ok(length);
+            }
+            let start = self.offset - self.offset % self.read_ahead as u64;
+            let chunk = (self.size - start).min(self.read_ahead as u64) as usize;
+            let mut buffer = std::mem::take(&mut self.buffer);
+            buffer.resize(chunk, 0);
+            self.read_exact_at(start, &mut buffer)?;
+            self.buffer = buffer;
+            self.buffer_start = start;
+        }
+        let from = (self.offset - self.buffer_start) as usize;
+        let length = length.min(self.buffer.len() - from);
+        buf[..length].copy_from_slice(&self.buffer[from..from + length]);
+        self.offset += length as u64;
+        Ok(length)
+    }
+}
+
+impl std::io::Seek for ArchiveFile<'_> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let (base, delta) = match pos {
+            std::io::SeekFrom::Start(offset) => {
+                self.buffer.clear();
+                self.offset = offset;
+                return Ok(offset);
+            }
+            std::io::SeekFrom::End(delta) => (self.size, delta),
+            std::io::SeekFrom::Current(delta) => (self.offset, delta),
+        };
+        self.offset = base.checked_add_signed(delta).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        self.buffer.clear();
+        Ok(self.offset)
+    }
+}
+
+trait ReadSeek: std::io::Read + std::io::Seek + Send {}
+impl<T: std::io::Read + std::io::Seek + Send> ReadSeek for T {}
+
+/// The source of a reader opened with [`ZArchiveReader::from_reader`], which
+/// the C++ reader calls for every read from the archive. Each fetch reads at
+/// least [`READ_AHEAD`] bytes, and reads within the last fetch are served
+/// from memory, so consecutive blocks cost one request to the source.
+struct ReadSource {
+    inner: Box<dyn ReadSeek>,
+    size: u64,
+    cache: Vec<u8>,
+    cache_start: u64,
+}
+
+impl ReadSource {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> bool {
+        use std::io::{Read, Seek, SeekFrom};
+        let end = match offset.checked_add(buf.len() as u64) {
+            Some(end) if end <= self.size => end,
+            _ => return false,
+        };
+        if offset < self.cache_start || end > self.cache_start + self.cache.len() as u64 {
+            let length = (self.size - offset).min(buf.len().max(READ_AHEAD) as u64);
+            self.cache.resize(length as usize, 0);
+            self.cache_start = offset;
+            let fetched = self.inner.seek(SeekFrom::Start(offset)).is_ok()
+                && self.inner.read_exact(&mut self.cache).is_ok();
+            if !fetched {
+                self.cache.clear();
+                return false;
+            }
+        }
+        let start = (offset - self.cache_start) as usize;
+        buf.copy_from_slice(&self.cache[start..start + buf.len()]);
+        true
+    }
+}
+
+/// A profiling event reported to the callback set with
+/// [`ReaderBuilder::on_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReaderEvent<'a> {
+    /// The archive was opened and its index loaded.
+    Open {
+        path: &'a Path,
+        duration: std::time::Duration,
+    },
+    /// A path was resolved to a file in the archive.
+    Lookup {
+        path: &'a str,
+        found: bool,
+        duration: std::time::Duration,
+    },
+    /// Data was read from a file in the archive.
+    Read {
+        path: &'a str,
+        bytes: u64,
+        duration: std::time::Duration,
+    },
+    /// A recursive walk finished or was dropped, after yielding `entries`.
+    Walk {
+        entries: usize,
+        duration: std::time::Duration,
+    },
+}
+
+#[derive(Clone)]
+struct EventHook(std::sync::Arc<dyn Fn(ReaderEvent<'_>) + Send + Sync>);
+
+impl std::fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventHook")
+    }
+}
+
+/// Options for opening a [`ZArchiveReader`].
+#[derive(Debug, Clone)]
+pub struct ReaderBuilder {
+    strict: bool,
+    keep_open: bool,
+    retry: Option<(u32, std::time::Duration)>,
+    on_event: Option<EventHook>,
+}
+
+impl Default for ReaderBuilder {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            keep_open: true,
+            retry: None,
+            on_event: None,
+        }
+    }
+}
+
+impl ReaderBuilder {
+    /// Create a builder with the default options, the same ones
+    /// [`ZArchiveReader::open`] uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When enabled, the whole tree is scanned at open time and the archive is
+    /// rejected with [`ZArchiveError::DuplicateEntry`] if any directory holds
+    /// two entries with the same (case-insensitive) name.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether to hold the archive file open for the life of the reader
+    /// (the default). When disabled, the file is closed after every read and
+    /// reopened on the next one, which saves a descriptor per idle reader at
+    /// the cost of an open call per read. Blocks already in the reader's
+    /// cache are served without reopening. The file index is always loaded
+    /// up front, so lookups and directory iteration never touch the file.
+    pub fn keep_open(mut self, keep_open: bool) -> Self {
+        self.keep_open = keep_open;
+        self
+    }
+
+    /// Retry failed reads from the archive file up to `attempts` more times,
+    /// reopening the file and waiting `backoff` before the first retry and
+    /// twice as long before each one after. Only I/O failures are retried;
+    /// corrupt data still fails at once. Lookups and directory iteration use
+    /// the index loaded at open time, so they never need retrying. The
+    /// reader stays locked while it waits.
+    pub fn retry(mut self, attempts: u32, backoff: std::time::Duration) -> Self {
+        self.retry = Some((attempts, backoff));
+        self
+    }
+
+    /// Report timings to `callback` for profiling: opening the archive, file
+    /// lookups, reads (of whole files, ranges and streamed copies) and
+    /// recursive walks. Without a callback no timing is done at all.
+    pub fn on_event(mut self, callback: impl Fn(ReaderEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(EventHook(std::sync::Arc::new(callback)));
+        self
+    }
+
+    /// Open a ZArchive from a file with these options.
+    pub fn open(self, path: impl AsRef<Path>) -> Result<ZArchiveReader> {
+        let start = std::time::Instant::now();
+        let mut reader = ZArchiveReader::open(path.as_ref())?;
+        if let Some(hook) = self.on_event {
+            (hook.0)(ReaderEvent::Open {
+                path: path.as_ref(),
+                duration: start.elapsed(),
+            });
+            reader.1 = Some(hook);
+        }
+        if !self.keep_open {
+            reader.0.write().unwrap().pin_mut().SetKeepOpen(false)?;
+        }
+        if let Some((attempts, backoff)) = self.retry {
+            reader
+                .0
+                .write()
+                .unwrap()
+                .pin_mut()
+                .SetRetryPolicy(attempts, backoff.as_micros().try_into().unwrap_or(u64::MAX))?;
+        }
+        if self.strict {
+            let root = reader
+                .0
+                .write()
+                .unwrap()
+                .pin_mut()
+                .LookUp("", false, true)?;
+            if root == ZARCHIVE_INVALID_NODE {
+                return Err(ZArchiveError::MissingFile("archive root".to_owned()));
+            }
+            reader.check_duplicates(root, &mut String::new(), &mut ffi::DirEntry::default())?;
+        }
+        Ok(reader)
+    }
+}
+
+type RawReader = cxx::UniquePtr<ffi::ZArchiveReader>;
+
+fn file_size_in(reader: &mut RawReader, file: &str) -> Option<usize> {
+    let node_handle = reader.pin_mut().LookUp(file, true, false).ok()?;
+    if !reader.IsFile(node_handle).ok()? {
+        return None;
+    }
+    reader
+        .pin_mut()
+        .GetFileSize(node_handle)
+        .ok()
+        .map(|s| s as usize)
+}
+
+fn read_file_in(reader: &mut RawReader, file: &str) -> Result<Vec<u8>> {
+    let handle = reader.pin_mut().LookUp(file, true, false)?;
+    if handle == ZARCHIVE_INVALID_NODE {
+        Err(ZArchiveError::MissingFile(file.to_owned()))
+    } else if !reader.IsFile(handle)? {
+        Err(ZArchiveError::NotAFile(file.to_owned()))
+    } else {
+        let size = reader.pin_mut().GetFileSize(handle)?;
+        let mut buffer: Vec<u8> = Vec::with_capacity(size as usize);
+        unsafe {
+            let written = read_from_raw(reader, file, handle, 0, size, buffer.as_mut_ptr())?;
+            buffer.set_len(written.min(size) as usize);
+            if written != size {
+                return Err(ZArchiveError::ShortRead {
+                    expected: size,
+                    actual: written,
+                });
+            }
+        };
+        Ok(buffer)
+    }
+}
+
+fn read_from_file_in(
+    reader: &mut RawReader,
+    file: &str,
+    offset: u64,
+    length: u64,
+) -> Result<Vec<u8>> {
+    let handle = reader.pin_mut().LookUp(file, true, false)?;
+    if handle == ZARCHIVE_INVALID_NODE {
+        Err(ZArchiveError::MissingFile(file.to_owned()))
+    } else if !reader.IsFile(handle)? {
+        Err(ZArchiveError::NotAFile(file.to_owned()))
+    } else {
+        let size = reader.pin_mut().GetFileSize(handle)?;
+        if offset.checked_add(length).is_none_or(|end| end > size) {
+            return Err(ZArchiveError::OutOfBounds {
+                path: file.to_owned(),
+                start: offset,
+                end: offset.saturating_add(length),
+                size,
+            });
+        }
+        let mut buffer: Vec<u8> = Vec::with_capacity(length as usize);
+        unsafe {
+            let written = read_from_raw(reader, file, handle, offset, length, buffer.as_mut_ptr())?;
+            buffer.set_len(written.min(length) as usize);
+            if written != length {
+                return Err(ZArchiveError::ShortRead {
+                    expected: length,
+                    actual: written,
+                });
+            }
+        };
+        Ok(buffer)
+    }
+}
+
+fn files_in(
+    reader: &mut RawReader,
+    files: &mut Vec<String>,
+    node_handle: ZArchiveNodeHandle,
+    parent: &str,
+    dir_entry: &mut ffi::DirEntry,
+) -> Result<()> {
+    for i in 0..reader.GetDirEntryCount(node_handle)? {
+        if reader.GetDirEntry(node_handle, i, dir_entry)? {
+            let full_path = if !parent.is_empty() {
+                [parent, dir_entry.name].join("/")
+            } else {
+                dir_entry.name.to_owned()
+            };
+            if dir_entry.isFile {
+                files.push(full_path);
+            } else if dir_entry.isDirectory {
+                let next = reader.pin_mut().LookUp(&full_path, false, true)?;
+                if next != ZARCHIVE_INVALID_NODE {
+                    files_in(reader, files, next, &full_path, dir_entry)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A single-threaded ZArchive reader, opened with
+/// [`ZArchiveReader::open_unsync`].
+///
+/// It offers the core read API of [`ZArchiveReader`] but keeps the C++ reader
+/// in a [`RefCell`] instead of an [`RwLock`], skipping lock acquisition on
+/// every call. It is `!Send` and `!Sync` by design; share a
+/// [`ZArchiveReader`] instead if the reader must cross threads.
+pub struct ZArchiveReaderLocal(RefCell<RawReader>);
+
+impl std::fmt::Debug for ZArchiveReaderLocal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ZArchiveReaderLocal")
+    }
+}
+
+impl ZArchiveReaderLocal {
+    /// Get the size of a file in the archive, if the file exists.
+    pub fn file_size(&self, file: impl AsRef<Path>) -> Option<usize> {
+        file_size_in(&mut self.0.borrow_mut(), file.as_ref().to_str()?)
+    }
+
+    /// Read a file from the archive into a `Vec<u8>`, if the file exists.
+    pub fn read_file(&self, file: impl AsRef<Path>) -> Option<Vec<u8>> {
+        read_file_in(&mut self.0.borrow_mut(), file.as_ref().to_str()?).ok()
+    }
+
+    /// Read part of a file from the archive into a `Vec<u8>` using the specified
+    /// length and offet, if the file exists.
+    pub fn read_from_file(
+        &self,
+        file: impl AsRef<Path>,
+        offset: usize,
+        length: usize,
+    ) -> Option<Vec<u8>> {
+        read_from_file_in(
+            &mut self.0.borrow_mut(),
+            file.as_ref().to_str()?,
+            offset as u64,
+            length as u64,
+        )
+        .ok()
+    }
+
+    /// Read a file from the archive into a `Vec<u8>`, failing with
+    /// [`ZArchiveError::MissingFile`] if there is no such entry,
+    /// [`ZArchiveError::NotAFile`] if the entry is a directory, or with the
+    /// underlying error if the read itself fails.
+    pub fn try_read_file(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        read_file_in(&mut self.0.borrow_mut(), file)
+    }
+
+    /// Read part of a file from the archive into a `Vec<u8>`, failing with
+    /// [`OutOfBounds`](ZArchiveError::OutOfBounds) if the range doesn't lie
+    /// within the file. See [`ZArchiveReader::try_read_from_file`].
+    pub fn try_read_from_file(
+        &self,
+        file: impl AsRef<Path>,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        read_from_file_in(&mut self.0.borrow_mut(), file, offset, length)
+    }
+
+    /// Extract a file from the archive to disk. If `dest` is an existing
+    /// directory, the file is extracted into it keeping its relative path in
+    /// the archive; otherwise it is written to exactly `dest`. Missing parent
+    /// directories are created.
+    pub fn extract_file(&self, file: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let data = self.try_read_file(&file)?;
+        let dest = if dest.as_ref().is_dir() {
+            dest.as_ref().join(file.as_ref())
+        } else {
+            dest.as_ref().to_path_buf()
+        };
+        dest.parent().map(std::fs::create_dir_all).transpose()?;
+        std::fs::write(dest, data)?;
+        Ok(())
+    }
+
+    /// Get a list of all the files in the archive.
+    pub fn get_files(&self) -> Result<Vec<String>> {
+        let mut reader = self.0.borrow_mut();
+        let mut files = vec![];
+        let root = reader.pin_mut().LookUp("", false, true)?;
+        if root != ZARCHIVE_INVALID_NODE {
+            let mut dir_entry = ffi::DirEntry::default();
+            files_in(&mut reader, &mut files, root, "", &mut dir_entry)?;
+        }
+        Ok(files)
+    }
+}
+
+/// Represents an open ZArchive, wrapping the C++ type.  
+///
+/// It holds an open file handle to the archive on disk, which it retains until
+/// destroyed. The archive is read-only, but the C++ struct mutates constantly
+/// for many operations. For this reason, the Rust struct wraps it in an
+/// [`RwLock`](std::sync::RwLock) to provide a simple immutable interface that
+/// works as expected in any context, including mulithreaded.
+///
+/// The format stores no origin information: apart from the format version
+/// (see [`header`](Self::header)), nothing records the tool, version or
+/// platform that created an archive, and the big-endian layout is the same
+/// everywhere.
+pub struct ZArchiveReader(
+    RwLock<cxx::UniquePtr<ffi::ZArchiveReader>>,
+    Option<EventHook>,
+);
+
+impl std::fmt::Debug for ZArchiveReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ZArchiveReader")
+    }
+}
+
+unsafe impl Send for ZArchiveReader {}
+unsafe impl Sync for ZArchiveReader {}
+
+/// The raw C++ archive reader type, for use with [`ZArchiveReader::as_raw`].
+#[cfg(feature = "unstable-ffi")]
+pub use ffi::{DirEntry as RawDirEntry, ZArchiveReader as RawZArchiveReader};
+
+impl ZArchiveReader {
+    /// Access the underlying C++ reader directly, for FFI calls not yet
+    /// covered by the safe API. Requires the `unstable-ffi` feature; this
+    /// interface may change in any release.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the invariants the safe wrapper relies on:
+    /// - Hold the lock for the full duration of any FFI call, taking the
+    ///   write lock for methods requiring `Pin<&mut _>`.
+    /// - Never replace, reset or move out of the `UniquePtr`.
+    /// - Only pass buffers to `ReadFromFile` which are valid for writes of
+    ///   the requested length.
+    /// - Do not let `RawDirEntry` names outlive the reader.
+    #[cfg(feature = "unstable-ffi")]
+    pub unsafe fn as_raw(&self) -> &RwLock<cxx::UniquePtr<RawZArchiveReader>> {
+        &self.0
+    }
+
+    /// Run `op`, timing it and passing the result to `report` if an event
+    /// callback is set.
+    fn timed<'p, T>(
+        &self,
+        op: impl FnOnce() -> T,
+        report: impl FnOnce(&T, std::time::Duration) -> ReaderEvent<'p>,
+    ) -> T {
+        match &self.1 {
+            None => op(),
+            Some(hook) => {
+                let start = std::time::Instant::now();
+                let result = op();
+                (hook.0)(report(&result, start.elapsed()));
+                result
+            }
+        }
+    }
+
+    /// Configure how an archive is opened. See [`ReaderBuilder`].
+    pub fn builder() -> ReaderBuilder {
+        ReaderBuilder::new()
+    }
+
+    /// Open a ZArchive from a file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self(
+            RwLock::new(ffi::OpenFromFile(path.as_ref().to_str().ok_or_else(
+                || ZArchiveError::InvalidFilePath(path.as_ref().to_string_lossy().to_string()),
+            )?)?),
+            None,
+        ))
+    }
+
+    /// Open a ZArchive held entirely in memory, e.g. one received over the
+    /// network. The reader takes ownership of the buffer and serves every read
+    /// from it, so it behaves just like a file-backed reader, except that
+    /// [`reopen`](Self::reopen) fails.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Ok(Self(RwLock::new(ffi::OpenFromMemory(data)?), None))
+    }
+
+    /// Open a ZArchive from any seekable source, such as an adapter that turns
+    /// reads into HTTP range requests against a remote archive. Only the
+    /// footer and index are read when opening; file data is fetched as it is
+    /// read. The source is read at least 1 MiB at a time and reads within the
+    /// last fetch are served from memory, so the neighbouring blocks of a
+    /// file cost a single request rather than one each. [`reopen`](Self::reopen)
+    /// is not supported.
+    ///
+    /// Any `Read + Seek` type works, here an in-memory [`Cursor`]:
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use zarchive::reader::ZArchiveReader;
+    ///
+    /// let data = std::fs::read("test/crafting.zar")?;
+    /// let archive = ZArchiveReader::from_reader(Cursor::new(data))?;
+    /// let rules = archive.try_read_file("rules.txt")?;
+    /// assert_eq!(rules.len(), 300);
+    /// # Ok::<(), zarchive::ZArchiveError>(())
+    /// ```
+    ///
+    /// [`Cursor`]: std::io::Cursor
+    pub fn from_reader(
+        mut source: impl std::io::Read + std::io::Seek + Send + 'static,
+    ) -> Result<Self> {
+        let size = source.seek(std::io::SeekFrom::End(0))?;
+        let source = Box::new(ReadSource {
+            inner: Box::new(source),
+            size,
+            cache: vec![],
+            cache_start: 0,
+        });
+        Ok(Self(RwLock::new(ffi::OpenFromSource(source, size)?), None))
+    }
+
+    /// Open a ZArchive embedded in a larger file, treating the `len` bytes
+    /// starting at `offset` as the whole archive. Every read from the file is
+    /// shifted by `offset`, so [`copy_archive_to`](Self::copy_archive_to)
+    /// copies only the embedded archive.
+    pub fn open_at(path: impl AsRef<Path>, offset: u64, len: u64) -> Result<Self> {
+        Ok(Self(
+            RwLock::new(ffi::OpenFromFileRange(
+                path.as_ref().to_str().ok_or_else(|| {
+                    ZArchiveError::InvalidFilePath(path.as_ref().to_string_lossy().to_string())
+                })?,
+                offset,
+                len,
+            )?),
+            None,
+        ))
+    }
+
+    /// Open the archive again from the path it was opened from, e.g. after
+    /// another process rewrote it. The file index and block cache are
+    /// replaced, so every holder of this reader sees the new contents. On
+    /// failure the reader keeps the archive it had.
+    pub fn reopen(&self) -> Result<()> {
+        let mut reader = self.0.write().unwrap();
+        let reopened = reader.pin_mut().Reopen()?;
+        *reader = reopened;
+        Ok(())
+    }
+
+    /// Open a ZArchive from a file for use on a single thread. See
+    /// [`ZArchiveReaderLocal`].
+    pub fn open_unsync(path: impl AsRef<Path>) -> Result<ZArchiveReaderLocal> {
+        Self::open(path)
+            .map(|reader| ZArchiveReaderLocal(RefCell::new(reader.0.into_inner().unwrap())))
+    }
+
+    /// Get the structural parameters of the archive, as read from its footer
+    /// and tables when it was opened.
+    pub fn header(&self) -> Result<ArchiveHeader> {
+        let reader = self.0.read().unwrap();
+        Ok(ArchiveHeader {
+            version: reader.GetVersion(),
+            block_size: BLOCK_SIZE as u32,
+            block_count: reader.GetBlockCount(),
+            offset_record_count: reader.GetOffsetRecordCount(),
+            compressed_data_size: reader.GetCompressedDataSize(),
+            file_count: reader.GetFileCount(),
+        })
+    }
+
+    /// Copy the raw archive, exactly as stored on disk, to a writer. Returns
+    /// the number of bytes written.
+    pub fn copy_archive_to(&self, w: &mut impl Write) -> Result<u64> {
+        let size = self.0.write().unwrap().pin_mut().GetArchiveSize()?;
+        let mut buffer = vec![0; BLOCK_SIZE.min(size as usize)];
+        let mut offset = 0;
+        while offset < size {
+            let length = (size - offset).min(BLOCK_SIZE as u64);
+            let read = unsafe {
+                self.0.write().unwrap().pin_mut().ReadArchiveBytes(
+                    offset,
+                    length,
+                    buffer.as_mut_ptr(),
+                )?
+            };
+            if read != length {
+                return Err(ZArchiveError::ShortRead {
+                    expected: length,
+                    actual: read,
+                });
+            }
+            w.write_all(&buffer[..read as usize])?;
+            offset += read;
+        }
+        Ok(offset)
+    }
+
+    /// Get the size of a file in the archive, if the file exists.
+    pub fn file_size(&self, file: impl AsRef<Path>) -> Option<usize> {
+        let file = file.as_ref().to_str()?;
+        self.timed(
+            || file_size_in(&mut self.0.write().unwrap(), file),
+            |size, duration| ReaderEvent::Lookup {
+                path: file,
+                found: size.is_some(),
+                duration,
+            },
+        )
+    }
+
+    /// Read a file from the archive into a `Vec<u8>`, if the file exists. Use
+    /// [`try_read_file`](Self::try_read_file) to find out why a read failed.
+    pub fn read_file(&self, file: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.try_read_file(file).ok()
+    }
+
+    /// Read a file from the archive into a `Vec<u8>`, failing with
+    /// [`ZArchiveError::MissingFile`] if there is no such entry,
+    /// [`ZArchiveError::NotAFile`] if the entry is a directory, or with the
+    /// underlying error if the read itself fails.
+    pub fn try_read_file(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        self.timed(
+            || read_file_in(&mut self.0.write().unwrap(), file),
+            |data, duration| ReaderEvent::Read {
+                path: file,
+                bytes: data.as_ref().map_or(0, |data| data.len() as u64),
+                duration,
+            },
+        )
+    }
+
+    /// Read a file of at most 4 KiB from the archive into an inline buffer,
+    /// without any heap allocation. Larger files fail with
+    /// [`ZArchiveError::FileTooLarge`].
+    pub fn read_small(&self, file: impl AsRef<Path>) -> Result<SmallVec<[u8; 4096]>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        self.timed(
+            || {
+                let handle = self.file_handle(file)?;
+                let mut reader = self.0.write().unwrap();
+                let size = reader.pin_mut().GetFileSize(handle)?;
+                let mut buffer = SmallVec::<[u8; 4096]>::new();
+                if size > buffer.inline_size() as u64 {
+                    return Err(ZArchiveError::FileTooLarge {
+                        path: file.to_owned(),
+                        size,
+                        limit: buffer.inline_size() as u64,
+                    });
+                }
+                unsafe {
+                    let written =
+                        read_from_raw(&mut reader, file, handle, 0, size, buffer.as_mut_ptr())?;
+                    buffer.set_len(written.min(size) as usize);
+                    if written != size {
+                        return Err(ZArchiveError::ShortRead {
+                            expected: size,
+                            actual: written,
+                        });
+                    }
+                }
+                Ok(buffer)
+            },
+            |result, duration| ReaderEvent::Read {
+                path: file,
+                bytes: result.as_ref().map_or(0, |data| data.len() as u64),
+                duration,
+            },
+        )
+    }
+
+    /// Read a file from the archive into a `Vec<u8>`, treating a missing file
+    /// as empty. Only [`ZArchiveError::MissingFile`] falls back to an empty
+    /// `Vec`; directories and failed reads are still returned as errors. See
+    /// [`try_read_file`](Self::try_read_file).
+    pub fn read_file_or_empty(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        match self.try_read_file(file) {
+            Err(ZArchiveError::MissingFile(_)) => Ok(vec![]),
+            result => result,
+        }
+    }
+
+    /// Read a file from the archive given its path as separate segments, e.g.
+    /// `["content", "Pack", "Bootup.pack"]`. The segments are matched one by
+    /// one without joining them into a path first. Returns `None` if the file
+    /// doesn't exist, and [`ZArchiveError::NotAFile`] if it is a directory.
+    pub fn read_path_segments(&self, segments: &[&str]) -> Result<Option<Vec<u8>>> {
+        let mut reader = self.0.write().unwrap();
+        let handle = reader.pin_mut().LookUpSegments(segments)?;
+        if handle == ZARCHIVE_INVALID_NODE {
+            return Ok(None);
+        }
+        if !reader.IsFile(handle)? {
+            return Err(ZArchiveError::NotAFile(segments.join("/")));
+        }
+        let size = reader.pin_mut().GetFileSize(handle)?;
+        let mut buffer: Vec<u8> = Vec::with_capacity(size as usize);
+        unsafe {
+            let written = read_from_raw(
+                &mut reader,
+                &segments.join("/"),
+                handle,
+                0,
+                size,
+                buffer.as_mut_ptr(),
+            )?;
+            buffer.set_len(written.min(size) as usize);
+            if written != size {
+                return Err(ZArchiveError::ShortRead {
+                    expected: size,
+                    actual: written,
+                });
+            }
+        };
+        Ok(Some(buffer))
+    }
+
+    /// Extract a file from the archive to disk, if the file exists. If the destination
+    /// is an existing directory, the file will be extracted into the directory with its
+    /// relative path in the archive. Otherwise it will be extracted to the destination
+    /// path as-is.
+    #[deprecated(note = "use `extract_file_to` or `extract_file_into` instead")]
+    pub fn extract_file(&self, file: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        if dest.as_ref().is_dir() {
+            self.extract_file_into(file, dest)
+        } else {
+            self.extract_file_to(file, dest)
+        }
+    }
+
+    /// Extract a file from the archive into a directory, keeping its relative
+    /// path in the archive. Missing parent directories are created.
+    pub fn extract_file_into(
+        &self,
+        file: impl AsRef<Path>,
+        dest_dir: impl AsRef<Path>,
+    ) -> Result<()> {
+        self.extract_file_to(&file, dest_dir.as_ref().join(file.as_ref()))
+    }
+
+    /// Extract a file from the archive to exactly the given path. Missing parent
+    /// directories are created.
+    pub fn extract_file_to(&self, file: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let dest = dest.as_ref();
+        let handle = self.file_handle(file)?;
+        dest.parent().map(std::fs::create_dir_all).transpose()?;
+        let mut reader = self.0.write().unwrap();
+        let size = reader.pin_mut().GetFileSize(handle)?;
+        let mut dest_handle = std::fs::File::create(dest)?;
+        dest_handle.set_len(size)?;
+        let mut buffer: Vec<u8> = Vec::with_capacity(size as usize);
+        unsafe {
+            let written = read_from_raw(&mut reader, file, handle, 0, size, buffer.as_mut_ptr())?;
+            buffer.set_len(written.min(size) as usize);
+            if written != size {
+                return Err(ZArchiveError::ShortRead {
+                    expected: size,
+                    actual: written,
+                });
+            }
+        };
+        std::io::BufWriter::new(&mut dest_handle).write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Extract a file from the archive to exactly the given path, decompressing
+    /// straight into a memory map of the destination file. No intermediate
+    /// buffer is allocated, which keeps memory use flat for very large files.
+    /// Missing parent directories are created. Requires the `memmap2`
+    /// feature.
+    #[cfg(feature = "memmap2")]
+    pub fn extract_file_mmap(&self, file: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        const CHUNK_SIZE: u64 = 16 * BLOCK_SIZE as u64;
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let dest = dest.as_ref();
+        let handle = self.file_handle(file)?;
+        dest.parent().map(std::fs::create_dir_all).transpose()?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        let dest_handle = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dest)?;
+        dest_handle.set_len(size)?;
+        if size == 0 {
+            return Ok(());
+        }
+        let mut map = unsafe { memmap2::MmapMut::map_mut(&dest_handle)? };
+        // Read a chunk at a time so other users of the reader aren't locked
+        // out for the whole file.
+        let mut offset = 0;
+        while offset < size {
+            let length = (size - offset).min(CHUNK_SIZE);
+            let written = unsafe {
+                read_from_raw(
+                    &mut self.0.write().unwrap(),
+                    file,
+                    handle,
+                    offset,
+                    length,
+                    map.as_mut_ptr().add(offset as usize),
+                )
+            }?;
+            if written != length {
+                return Err(ZArchiveError::ShortRead {
+                    expected: length,
+                    actual: written,
+                });
+            }
+            offset += written;
+        }
+        map.flush()?;
+        Ok(())
+    }
+
+    /// Repack the whole archive as a standard zip file at `dest`, keeping every
+    /// path. Each file is streamed into the zip a block at a time rather than
+    /// read whole. Errors from the zip writer are reported as
+    /// [`ZArchiveError::IOError`]. Requires the `zip` feature.
+    #[cfg(feature = "zip")]
+    pub fn repack_as_zip(&self, dest: impl AsRef<Path>, options: ZipOptions) -> Result<()> {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(dest)?);
+        let file_options =
+            zip::write::FileOptions::default().compression_method(options.compression);
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if let Some(size) = entry.size() {
+                zip.start_file(
+                    entry.full_path(),
+                    file_options.large_file(size as u64 >= u32::MAX as u64),
+                )
+                .map_err(std::io::Error::from)?;
+                self.copy_range_to(entry.full_path(), 0..size as u64, &mut zip)?;
+            }
+        }
+        if options.include_empty_dirs {
+            for dir in self.empty_dirs()? {
+                zip.add_directory(dir, file_options)
+                    .map_err(std::io::Error::from)?;
+            }
+        }
+        zip.finish().map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Extract a file from the archive to a new temporary file, for handing to
+    /// tools which only accept a path. The temporary file keeps the original
+    /// extension and is deleted when the returned handle is dropped. Requires
+    /// the `tempfile` feature.
+    #[cfg(feature = "tempfile")]
+    pub fn extract_to_temp(&self, file: impl AsRef<Path>) -> Result<tempfile::NamedTempFile> {
+        let name = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(name)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        let suffix = file
+            .as_ref()
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let mut temp = tempfile::Builder::new().suffix(&suffix).tempfile()?;
+        self.copy_range_to(name, 0..size, &mut temp)?;
+        temp.flush()?;
+        Ok(temp)
+    }
+
+    /// Estimate the disk space needed to extract the archive: the size of every
+    /// file rounded up to a whole 4 KiB filesystem block, plus one block per
+    /// directory.
+    pub fn required_space(&self) -> Result<u64> {
+        const FS_BLOCK: u64 = 4096;
+        let mut total = 0;
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            total += if entry.is_file() {
+                entry.size.div_ceil(FS_BLOCK) * FS_BLOCK
+            } else {
+                FS_BLOCK
+            };
+        }
+        Ok(total)
+    }
+
+    /// Check that the filesystem holding `dest` has room to extract the
+    /// archive, failing with
+    /// [`ExtractLimitExceeded`](ZArchiveError::ExtractLimitExceeded) if not.
+    /// Returns the [required space](Self::required_space). `dest` need not
+    /// exist yet. Requires the `fs2` feature.
+    #[cfg(feature = "fs2")]
+    pub fn check_space(&self, dest: impl AsRef<Path>) -> Result<u64> {
+        let required = self.required_space()?;
+        let existing = dest
+            .as_ref()
+            .ancestors()
+            .find(|path| path.exists())
+            .unwrap_or_else(|| Path::new("."));
+        let available = fs2::available_space(existing)?;
+        if required > available {
+            return Err(ZArchiveError::ExtractLimitExceeded {
+                required,
+                available,
+            });
+        }
+        Ok(required)
+    }
+
+    /// Extract the entire archive to disk.
+    pub fn extract(&self, dest: impl AsRef<Path>) -> Result<()> {
+        self.extract_with_options(dest, ExtractOptions::default())
+    }
+
+    /// Extract the entire archive to disk like [`extract`](Self::extract),
+    /// calling `callback` before each file is written. The archive is walked
+    /// up front so the totals are known from the first report. Empty
+    /// directories are created too, after all files are written.
+    pub fn extract_with_progress(
+        &self,
+        dest: impl AsRef<Path>,
+        mut callback: impl FnMut(ExtractProgress<'_>),
+    ) -> Result<()> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            return Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ));
+        }
+        let mut files: Vec<(String, u64)> = Vec::new();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                files.push((entry.path, entry.size));
+            }
+        }
+        let files_total = files.len();
+        let bytes_total = files.iter().map(|(_, size)| size).sum();
+        let mut bytes_done = 0;
+        for (files_done, (file, size)) in files.iter().enumerate() {
+            callback(ExtractProgress {
+                files_done,
+                files_total,
+                bytes_done,
+                bytes_total,
+                current_file: file,
+            });
+            self.extract_file_to(file, dest.join(file))?;
+            bytes_done += size;
+        }
+        for dir in self.empty_dirs()? {
+            std::fs::create_dir_all(dest.join(dir))?;
+        }
+        Ok(())
+    }
+
+    /// Extract the entire archive to disk with the given options.
+    pub fn extract_with_options(
+        &self,
+        dest: impl AsRef<Path>,
+        options: ExtractOptions,
+    ) -> Result<()> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ))
+        } else {
+            let prefix = if options.strip_common_prefix {
+                self.common_prefix()?.map(|prefix| prefix + "/")
+            } else {
+                None
+            };
+            let out_path = |path: &str| match prefix.as_deref() {
+                Some(prefix) if path.starts_with(prefix) => dest.join(&path[prefix.len()..]),
+                Some(prefix) if path == &prefix[..prefix.len() - 1] => dest.to_owned(),
+                _ => dest.join(path),
+            };
+            self.get_files()?.into_iter().try_for_each(|file| {
+                let dest = out_path(&file);
+                if !dest.parent().unwrap().exists() {
+                    std::fs::create_dir_all(dest.parent().unwrap())?;
+                }
+                self.extract_file_to(&file, &dest)
+            })?;
+            if options.create_empty_dirs {
+                for dir in self.empty_dirs()? {
+                    std::fs::create_dir_all(out_path(&dir))?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Extract the entire archive to disk in parallel on the rayon thread
+    /// pool, picking a strategy by file size. Small files are extracted many
+    /// at a time, each worker thread reading through its own reader opened
+    /// once from the same path so they don't contend for this reader's lock.
+    /// Files of 1 MiB or more are extracted one at a time, with their blocks
+    /// decompressed in parallel by
+    /// [`read_file_parallel`](Self::read_file_parallel). Readers not opened
+    /// from a file are shared by the worker threads instead. Empty
+    /// directories are created too. Requires the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn extract_adaptive(&self, dest: impl AsRef<Path>) -> Result<()> {
+        use rayon::prelude::*;
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            return Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ));
+        }
+        let (mut small, mut large) = (vec![], vec![]);
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if !entry.is_file() {
+                continue;
+            }
+            if entry.size >= ADAPTIVE_LARGE_FILE {
+                large.push(entry.path);
+            } else {
+                small.push(entry.path);
+            }
+        }
+        for dir in self.empty_dirs()? {
+            std::fs::create_dir_all(dest.join(dir))?;
+        }
+        // One reader per pool thread, opened up front and picked by thread
+        // index, so the archive is opened once per thread rather than once
+        // per rayon split.
+        let readers: Vec<ZArchiveReader> = if small.is_empty() {
+            vec![]
+        } else {
+            (0..rayon::current_num_threads())
+                .map_while(|_| {
+                    let reader = self.0.write().unwrap().pin_mut().Reopen().ok()?;
+                    Some(ZArchiveReader(RwLock::new(reader), None))
+                })
+                .collect()
+        };
+        small.par_iter().try_for_each(|file| {
+            rayon::current_thread_index()
+                .and_then(|index| readers.get(index))
+                .unwrap_or(self)
+                .extract_file_into(file, dest)
+        })?;
+        for file in large {
+            let data = self.read_file_parallel(&file)?;
+            let out = dest.join(&file);
+            out.parent().map(std::fs::create_dir_all).transpose()?;
+            std::fs::write(out, data)?;
+        }
+        Ok(())
+    }
+
+    /// List the directories in the archive which have no entries at all.
+    pub fn empty_dirs(&self) -> Result<Vec<String>> {
+        let mut dirs = vec![];
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if !entry.is_dir() {
+                continue;
+            }
+            let mut reader = self.0.write().unwrap();
+            let handle = reader.pin_mut().LookUp(entry.full_path(), false, true)?;
+            if handle != ZARCHIVE_INVALID_NODE && reader.GetDirEntryCount(handle)? == 0 {
+                dirs.push(entry.path);
+            }
+        }
+        Ok(dirs)
+    }
+
+    /// Extract the entire archive to disk, calling `on_file` after each file
+    /// with its path and either the number of bytes written or the error that
+    /// stopped it. Failed files don't stop the extraction.
+    pub fn extract_each(
+        &self,
+        dest: impl AsRef<Path>,
+        mut on_file: impl FnMut(&str, Result<u64>),
+    ) -> Result<()> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            return Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ));
+        }
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                let result = self
+                    .extract_file_into(entry.full_path(), dest)
+                    .map(|_| entry.size);
+                on_file(entry.full_path(), result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the files in the archive for which `keep` returns true, given
+    /// each file's path and size. Skipped files are never read.
+    pub fn extract_filtered(
+        &self,
+        dest: impl AsRef<Path>,
+        keep: impl Fn(&str, Option<u64>) -> bool,
+    ) -> Result<()> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ))
+        } else {
+            for entry in self.walk_with_depth()? {
+                let (_, entry) = entry?;
+                if entry.is_file() && keep(entry.full_path(), entry.size().map(|s| s as u64)) {
+                    self.extract_file_into(entry.full_path(), dest)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Extract the entire archive to disk, hashing each file as it is written
+    /// and returning a manifest of the extracted files. This produces the same
+    /// tree as [`extract`](Self::extract) in a single pass.
+    pub fn extract_with_manifest(&self, dest: impl AsRef<Path>) -> Result<Manifest> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            return Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ));
+        }
+        let mut buffer = Vec::new();
+        let mut manifest = Manifest::default();
+        for file in self.get_files()? {
+            let out = dest.join(&file);
+            if let Some(parent) = out.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            self.read_file_into(&file, &mut buffer)?;
+            std::fs::write(&out, &buffer)?;
+            manifest.entries.push(ManifestEntry {
+                sha256: sha::sha256(&buffer),
+                size: buffer.len() as u64,
+                path: file,
+            });
+        }
+        Ok(manifest)
+    }
+
+    /// Extract only the files which are missing from the destination or differ
+    /// from the archive, leaving matching files untouched. Files are compared
+    /// by size, and if `compare_hash` is set, by SHA-256 when the sizes match.
+    pub fn extract_changed(
+        &self,
+        dest: impl AsRef<Path>,
+        compare_hash: bool,
+    ) -> Result<ExtractReport> {
+        let dest = dest.as_ref();
+        if dest.is_file() {
+            return Err(ZArchiveError::InvalidDestination(
+                dest.to_string_lossy().to_string(),
+            ));
+        }
+        let mut report = ExtractReport::default();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if !entry.is_file() {
+                continue;
+            }
+            let out = dest.join(entry.full_path());
+            let path = entry.full_path().to_owned();
+            match std::fs::metadata(&out) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    self.extract_file_to(&path, &out)?;
+                    report.added.push(path);
+                }
+                Err(e) => return Err(e.into()),
+                Ok(metadata) => {
+                    let changed = metadata.len() != entry.size
+                        || (compare_hash && !self.verify_extracted(&path, &out)?);
+                    if changed {
+                        self.extract_file_to(&path, &out)?;
+                        report.updated.push(path);
+                    } else {
+                        report.skipped.push(path);
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Compare a directory on disk with the archive, listing the files that
+    /// would have to be added, deleted or replaced for the archive to match
+    /// it. Files present in both are compared with
+    /// [`verify_extracted`](Self::verify_extracted). Like lookups, paths are
+    /// matched case-insensitively.
+    pub fn dir_changes(&self, dir: impl AsRef<Path>) -> Result<DirChanges> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(ZArchiveError::InvalidDestination(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+        let mut changes = DirChanges::default();
+        let mut on_disk = std::collections::HashSet::new();
+        let mut stack = vec![dir.to_path_buf()];
+        while let Some(current) = stack.pop() {
+            for entry in std::fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+                let file = archive_path(dir, &path)?;
+                match self.file_handle(&file) {
+                    Err(ZArchiveError::MissingFile(_)) => changes.added.push(file.clone()),
+                    Err(e) => return Err(e),
+                    Ok(_) if !self.verify_extracted(&file, &path)? => {
+                        changes.modified.push(file.clone())
+                    }
+                    Ok(_) => (),
+                }
+                on_disk.insert(file.to_ascii_lowercase());
+            }
+        }
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() && !on_disk.contains(&entry.full_path().to_ascii_lowercase()) {
+                changes.deleted.push(entry.path);
+            }
+        }
+        changes.added.sort();
+        changes.deleted.sort();
+        changes.modified.sort();
+        Ok(changes)
+    }
+
+    /// Read a file from the archive into a pooled buffer and pass its contents
+    /// to a closure, returning the closure's result. Buffers are kept in a small
+    /// per-thread pool and reused across calls, which avoids a fresh allocation
+    /// for every read when serving many files.
+    pub fn with_read_buffer<R>(
+        &self,
+        file: impl AsRef<Path>,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> Result<R> {
+        let mut buffer = READ_BUFFERS
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_default();
+        let result = self.read_file_into(file, &mut buffer).map(|_| f(&buffer));
+        READ_BUFFERS.with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < MAX_POOLED_BUFFERS {
+                pool.push(buffer);
+            }
+        });
+        result
+    }
+
+    /// Walk the archive, passing the path and contents of each file to a
+    /// closure. Every file is read into the same buffer, so the data is only
+    /// borrowed for the duration of the call. Returning
+    /// [`ControlFlow::Break`] stops the walk early.
+    pub fn for_each_file_data(
+        &self,
+        mut f: impl FnMut(&str, &[u8]) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let mut buffer = Vec::new();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                self.read_file_into(entry.full_path(), &mut buffer)?;
+                if f(entry.full_path(), &buffer).is_break() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find references between files which point at nothing. `extractor` is
+    /// given the path and contents of every file and returns the paths that
+    /// file refers to; each referenced path that is not a file in the archive
+    /// is reported as a `(referencing file, missing path)` pair, in walk
+    /// order.
+    pub fn check_references(
+        &self,
+        extractor: impl Fn(&str, &[u8]) -> Vec<String>,
+    ) -> Result<Vec<(String, String)>> {
+        let mut dangling = vec![];
+        self.for_each_file_data(|file, data| {
+            for target in extractor(file, data) {
+                if self.file_size(&target).is_none() {
+                    dangling.push((file.to_owned(), target));
+                }
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok(dangling)
+    }
+
+    /// Read a file from the archive and run it through the decoder registered
+    /// for its magic, returning the decoded bytes. Files with no matching
+    /// decoder are returned as stored.
+    pub fn read_file_decoded(
+        &self,
+        file: impl AsRef<Path>,
+        decoders: &DecoderRegistry,
+    ) -> Result<Vec<u8>> {
+        self.with_read_buffer(file, |data| decoders.decode(data))?
+    }
+
+    /// Append a file from the archive to the end of `arena`, returning the
+    /// range of the arena it occupies. Reading many small files into one arena
+    /// shares a single allocation between them.
+    pub fn read_into_arena(
+        &self,
+        file: impl AsRef<Path>,
+        arena: &mut Vec<u8>,
+    ) -> Result<Range<usize>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let mut reader = self.0.write().unwrap();
+        let size = reader.pin_mut().GetFileSize(handle)?;
+        let start = arena.len();
+        arena.reserve(size as usize);
+        unsafe {
+            let written = read_from_raw(
+                &mut reader,
+                file,
+                handle,
+                0,
+                size,
+                arena.as_mut_ptr().add(start),
+            )?;
+            arena.set_len(start + written.min(size) as usize);
+            if written != size {
+                return Err(ZArchiveError::ShortRead {
+                    expected: size,
+                    actual: written,
+                });
+            }
+        };
+        Ok(start..arena.len())
+    }
+
+    /// Read a file from the archive into `buf`, replacing its contents, and
+    /// return the number of bytes read. Keeping one buffer across many reads
+    /// avoids allocating for each file once it has grown large enough.
+    pub fn read_file_into(&self, file: impl AsRef<Path>, buf: &mut Vec<u8>) -> Result<usize> {
+        buf.clear();
+        self.read_into_arena(file, buf).map(|range| range.len())
+    }
+
+    /// Check whether two paths resolve to the same entry in the archive, e.g.
+    /// paths differing only in case. Fails with
+    /// [`MissingFile`](ZArchiveError::MissingFile) if either path doesn't exist.
+    pub fn same_node(&self, a: &str, b: &str) -> Result<bool> {
+        let mut reader = self.0.write().unwrap();
+        let a_handle = reader.pin_mut().LookUp(a, true, true)?;
+        if a_handle == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile(a.to_owned()));
+        }
+        let b_handle = reader.pin_mut().LookUp(b, true, true)?;
+        if b_handle == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile(b.to_owned()));
+        }
+        Ok(a_handle == b_handle)
+    }
+
+    /// Look up a file in the archive, returning its handle,
+    /// [`MissingFile`](ZArchiveError::MissingFile) if there is no such entry, or
+    /// [`NotAFile`](ZArchiveError::NotAFile) if the entry is a directory.
+    fn file_handle(&self, file: &str) -> Result<ZArchiveNodeHandle> {
+        self.timed(
+            || {
+                let handle = self
+                    .0
+                    .write()
+                    .unwrap()
+                    .pin_mut()
+                    .LookUp(file, true, false)?;
+                if handle == ZARCHIVE_INVALID_NODE {
+                    Err(ZArchiveError::MissingFile(file.to_owned()))
+                } else if !self.0.read().unwrap().IsFile(handle)? {
+                    Err(ZArchiveError::NotAFile(file.to_owned()))
+                } else {
+                    Ok(handle)
+                }
+            },
+            |handle, duration| ReaderEvent::Lookup {
+                path: file,
+                found: handle.is_ok(),
+                duration,
+            },
+        )
+    }
+
+    /// Read a file from the archive in 64 KiB chunks, applying `f` to each
+    /// chunk in place before it is yielded, e.g. to decrypt or byte-swap a
+    /// large file while holding only one chunk at a time. Iteration stops
+    /// after the first error.
+    pub fn read_transformed<'a>(
+        &'a self,
+        file: impl AsRef<Path>,
+        mut f: impl FnMut(&mut [u8]) + 'a,
+    ) -> Result<impl Iterator<Item = Result<Vec<u8>>> + 'a> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        let file = file.to_owned();
+        let mut offset = 0;
+        Ok(std::iter::from_fn(move || {
+            if offset >= size {
+                return None;
+            }
+            let length = (size - offset).min(BLOCK_SIZE as u64);
+            let mut chunk: Vec<u8> = Vec::with_capacity(length as usize);
+            let result = unsafe {
+                read_from_raw(
+                    &mut self.0.write().unwrap(),
+                    &file,
+                    handle,
+                    offset,
+                    length,
+                    chunk.as_mut_ptr(),
+                )
+            };
+            let written = match result {
+                Ok(written) => written.min(length),
+                Err(e) => {
+                    offset = size;
+                    return Some(Err(e));
+                }
+            };
+            unsafe { chunk.set_len(written as usize) };
+            if written != length {
+                offset = size;
+                return Some(Err(ZArchiveError::ShortRead {
+                    expected: length,
+                    actual: written,
+                }));
+            }
+            offset += written;
+            f(&mut chunk);
+            Some(Ok(chunk))
+        }))
+    }
+
+    /// Open a file in the archive as a stream implementing [`Read`] and
+    /// [`Seek`](std::io::Seek), reading only the blocks that are actually
+    /// needed, for decoders that take `impl Read + Seek`. Seeking past the end
+    /// is allowed; reads there return no data. Small reads are buffered one
+    /// archive block (64 KiB) at a time; see
+    /// [`open_file_with_read_ahead`](Self::open_file_with_read_ahead).
+    ///
+    /// [`Read`]: std::io::Read
+    pub fn open_file(&self, file: impl AsRef<Path>) -> Result<ArchiveFile<'_>> {
+        self.open_file_with_read_ahead(file, BLOCK_SIZE)
+    }
+
+    /// Like [`open_file`](Self::open_file), but reads smaller than
+    /// `read_ahead` bytes fetch the `read_ahead`-aligned chunk around them
+    /// into a buffer that serves the following sequential reads. A size of 0
+    /// disables buffering so that every read goes to the archive.
+    pub fn open_file_with_read_ahead(
+        &self,
+        file: impl AsRef<Path>,
+        read_ahead: usize,
+    ) -> Result<ArchiveFile<'_>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        Ok(ArchiveFile {
+            reader: self,
+            path: file.to_owned(),
+            handle,
+            size,
+            offset: 0,
+            read_ahead,
+            buffer: vec![],
+            buffer_start: 0,
+        })
+    }
+
+    /// Read several files in the archive in order as one contiguous stream,
+    /// moving on to the next file when one runs out, e.g. to parse an asset
+    /// split across multiple entries. Every path is resolved up front.
+    pub fn concat_reader(&self, paths: &[&str]) -> Result<ConcatReader<'_>> {
+        let mut files = Vec::with_capacity(paths.len());
+        for &path in paths {
+            let handle = self.file_handle(path)?;
+            let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+            files.push((path.to_owned(), handle, size));
+        }
+        Ok(ConcatReader {
+            reader: self,
+            files,
+            index: 0,
+            offset: 0,
+        })
+    }
+
+    /// Stream a byte range of a file in the archive to a writer, one block at a
+    /// time, returning the number of bytes written. The range must lie within
+    /// the file. Useful for resuming an interrupted extraction.
+    pub fn copy_range_to(
+        &self,
+        file: impl AsRef<Path>,
+        range: Range<u64>,
+        w: &mut impl Write,
+    ) -> Result<u64> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        self.timed(
+            || {
+                let handle = self.file_handle(file)?;
+                let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+                if range.start > range.end || range.end > size {
+                    return Err(ZArchiveError::OutOfBounds {
+                        path: file.to_owned(),
+                        start: range.start,
+                        end: range.end,
+                        size,
+                    });
+                }
+                let mut buffer = vec![0; BLOCK_SIZE.min((range.end - range.start) as usize)];
+                let mut offset = range.start;
+                while offset < range.end {
+                    let length = (range.end - offset).min(BLOCK_SIZE as u64);
+                    let written = unsafe {
+                        read_from_raw(
+                            &mut self.0.write().unwrap(),
+                            file,
+                            handle,
+                            offset,
+                            length,
+                            buffer.as_mut_ptr(),
+                        )
+                    }?;
+                    if written != length {
+                        return Err(ZArchiveError::ShortRead {
+                            expected: length,
+                            actual: written,
+                        });
+                    }
+                    w.write_all(&buffer[..written as usize])?;
+                    offset += written;
+                }
+                Ok(offset - range.start)
+            },
+            |result, duration| ReaderEvent::Read {
+                path: file,
+                bytes: result.as_ref().map_or(0, |written| *written),
+                duration,
+            },
+        )
+    }
+
+    /// Compute the CRC-32 of a byte range of a file in the archive, streaming
+    /// it block by block. The range must lie within the file. Useful for
+    /// checking an already transferred prefix before resuming.
+    pub fn range_crc32(&self, file: impl AsRef<Path>, range: Range<u64>) -> Result<u32> {
+        let mut hasher = crc::Crc32::new();
+        self.copy_range_to(file, range, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
+    /// Compute the SHA-256 hash of a byte range of a file in the archive,
+    /// streaming it block by block. The range must lie within the file.
+    pub fn range_sha256(&self, file: impl AsRef<Path>, range: Range<u64>) -> Result<[u8; 32]> {
+        let mut hasher = sha::Sha256::new();
+        self.copy_range_to(file, range, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
+    /// Check a file extracted to disk against its entry in the archive, comparing
+    /// the size and then the SHA-256 hash of the contents.
+    pub fn verify_extracted(
+        &self,
+        file: impl AsRef<Path>,
+        disk_path: impl AsRef<Path>,
+    ) -> Result<bool> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        if std::fs::metadata(disk_path.as_ref())?.len() != size {
+            return Ok(false);
+        }
+        let mut disk_hasher = sha::Sha256::new();
+        std::io::copy(&mut std::fs::File::open(disk_path)?, &mut disk_hasher)?;
+        Ok(self.sha256_of_file(file)? == disk_hasher.finish())
+    }
+
+    /// Compute the SHA-256 hash of a file in the archive, streaming it block by
+    /// block rather than reading it whole.
+    pub fn sha256_of_file(&self, file: impl AsRef<Path>) -> Result<[u8; 32]> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        let mut hasher = sha::Sha256::new();
+        self.copy_range_to(file, 0..size, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
+    /// Read part of a file from the archive into a `Vec<u8>` using the specified
+    /// length and offet, if the file exists.
+    pub fn read_from_file(
+        &self,
+        file: impl AsRef<Path>,
+        offset: usize,
+        length: usize,
+    ) -> Option<Vec<u8>> {
+        let file = file.as_ref().to_str()?;
+        self.timed(
+            || {
+                read_from_file_in(
+                    &mut self.0.write().unwrap(),
+                    file,
+                    offset as u64,
+                    length as u64,
+                )
+                .ok()
+            },
+            |data, duration| ReaderEvent::Read {
+                path: file,
+                bytes: data.as_ref().map_or(0, |data| data.len() as u64),
+                duration,
+            },
+        )
+    }
+
+    /// Read part of a file from the archive into a `Vec<u8>`, reporting why the
+    /// read failed: [`MissingFile`](ZArchiveError::MissingFile) if there is
+    /// no such file, [`OutOfBounds`](ZArchiveError::OutOfBounds) if the range
+    /// doesn't lie within it, [`ShortRead`](ZArchiveError::ShortRead) if fewer
+    /// bytes than requested could be read, or the decompression error.
+    pub fn try_read_from_file(
+        &self,
+        file: impl AsRef<Path>,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        self.timed(
+            || {
+                let handle = self.file_handle(file)?;
+                let mut reader = self.0.write().unwrap();
+                let size = reader.pin_mut().GetFileSize(handle)?;
+                if offset.checked_add(length).is_none_or(|end| end > size) {
+                    return Err(ZArchiveError::OutOfBounds {
+                        path: file.to_owned(),
+                        start: offset,
+                        end: offset.saturating_add(length),
+                        size,
+                    });
+                }
+                let mut buffer: Vec<u8> = Vec::with_capacity(length as usize);
+                unsafe {
+                    let written = read_from_raw(
+                        &mut reader,
+                        file,
+                        handle,
+                        offset,
+                        length,
+                        buffer.as_mut_ptr(),
+                    )?;
+                    buffer.set_len(written.min(length) as usize);
+                    if written != length {
+                        return Err(ZArchiveError::ShortRead {
+                            expected: length,
+                            actual: written,
+                        });
+                    }
+                }
+                Ok(buffer)
+            },
+            |result, duration| ReaderEvent::Read {
+                path: file,
+                bytes: result.as_ref().map_or(0, |data| data.len() as u64),
+                duration,
+            },
+        )
+    }
+
+    /// Read exactly `N` bytes of a file in the archive starting at `offset`,
+    /// failing with [`OutOfBounds`](ZArchiveError::OutOfBounds) if they don't
+    /// all lie within the file.
+    fn read_array_at<const N: usize>(
+        &self,
+        file: impl AsRef<Path>,
+        offset: u64,
+    ) -> Result<[u8; N]> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let Some(end) = offset.checked_add(N as u64) else {
+            let handle = self.file_handle(file)?;
+            return Err(ZArchiveError::OutOfBounds {
+                path: file.to_owned(),
+                start: offset,
+                end: u64::MAX,
+                size: self.0.write().unwrap().pin_mut().GetFileSize(handle)?,
+            });
+        };
+        let mut bytes = [0; N];
+        self.copy_range_to(file, offset..end, &mut &mut bytes[..])?;
+        Ok(bytes)
+    }
+
+    /// Read a `u16` from a file in the archive at the given offset.
+    pub fn read_u16_at(&self, file: impl AsRef<Path>, offset: u64, endian: Endian) -> Result<u16> {
+        let bytes = self.read_array_at(file, offset)?;
+        Ok(match endian {
+            Endian::Big => u16::from_be_bytes(bytes),
+            Endian::Little => u16::from_le_bytes(bytes),
+        })
+    }
+
+    /// Read a `u32` from a file in the archive at the given offset.
+    pub fn read_u32_at(&self, file: impl AsRef<Path>, offset: u64, endian: Endian) -> Result<u32> {
+        let bytes = self.read_array_at(file, offset)?;
+        Ok(match endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Read an `i32` from a file in the archive at the given offset.
+    pub fn read_i32_at(&self, file: impl AsRef<Path>, offset: u64, endian: Endian) -> Result<i32> {
+        let bytes = self.read_array_at(file, offset)?;
+        Ok(match endian {
+            Endian::Big => i32::from_be_bytes(bytes),
+            Endian::Little => i32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Read a `u64` from a file in the archive at the given offset.
+    pub fn read_u64_at(&self, file: impl AsRef<Path>, offset: u64, endian: Endian) -> Result<u64> {
+        let bytes = self.read_array_at(file, offset)?;
+        Ok(match endian {
+            Endian::Big => u64::from_be_bytes(bytes),
+            Endian::Little => u64::from_le_bytes(bytes),
+        })
+    }
+
+    /// Map a byte range of a file in the archive to the ranges of the archive
+    /// file holding the compressed blocks it spans. Blocks stored back to back
+    /// are merged into one range, so a caller can issue one read per range.
+    pub fn physical_range(
+        &self,
+        file: impl AsRef<Path>,
+        logical_range: Range<u64>,
+    ) -> Result<Vec<Range<u64>>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        if logical_range.start > logical_range.end || logical_range.end > size {
+            return Err(ZArchiveError::OutOfBounds {
+                path: file.to_owned(),
+                start: logical_range.start,
+                end: logical_range.end,
+                size,
+            });
+        }
+        let mut ranges: Vec<Range<u64>> = vec![];
+        if logical_range.is_empty() {
+            return Ok(ranges);
+        }
+        let reader = self.0.read().unwrap();
+        let data_offset = reader.GetFileDataOffset(handle)?;
+        let first = (data_offset + logical_range.start) / BLOCK_SIZE as u64;
+        let last = (data_offset + logical_range.end - 1) / BLOCK_SIZE as u64;
+        for block in first..=last {
+            let start = reader.GetBlockPhysicalOffset(block)?;
+            let end = start + reader.GetBlockCompressedSize(block)? as u64;
+            match ranges.last_mut() {
+                Some(range) if range.end == start => range.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Check whether any of a file's data is stored compressed. Blocks which
+    /// zstd can't shrink are stored raw, so this is `false` for files made up
+    /// only of such blocks, e.g. already-compressed assets, and for empty
+    /// files. Blocks are shared between neighbouring files, so a small file
+    /// can report the compression of the data around it.
+    pub fn is_compressed(&self, file: impl AsRef<Path>) -> Result<bool> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        if size == 0 {
+            return Ok(false);
+        }
+        let reader = self.0.read().unwrap();
+        let data_offset = reader.GetFileDataOffset(handle)?;
+        let first = data_offset / BLOCK_SIZE as u64;
+        let last = (data_offset + size - 1) / BLOCK_SIZE as u64;
+        for block in first..=last {
+            if (reader.GetBlockCompressedSize(block)? as usize) < BLOCK_SIZE {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Get the uncompressed size of a file along with the compressed size of
+    /// the blocks holding its data. Blocks are shared between neighbouring
+    /// files, so the compressed size of a small file counts whole blocks and
+    /// can exceed its uncompressed size.
+    pub fn sizes(&self, file: impl AsRef<Path>) -> Result<(u64, u64)> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        if size == 0 {
+            return Ok((0, 0));
+        }
+        let reader = self.0.read().unwrap();
+        let data_offset = reader.GetFileDataOffset(handle)?;
+        let first = data_offset / BLOCK_SIZE as u64;
+        let last = (data_offset + size - 1) / BLOCK_SIZE as u64;
+        let mut compressed = 0;
+        for block in first..=last {
+            compressed += reader.GetBlockCompressedSize(block)? as u64;
+        }
+        Ok((size, compressed))
+    }
+
+    /// Read a file from the archive, decompressing its blocks in parallel on
+    /// the rayon thread pool. The compressed blocks are read from the archive
+    /// first, bypassing the reader's block cache, and then decompressed
+    /// concurrently into place. The reader is locked for 16 blocks at a time
+    /// while reading, so other threads can use it in between. This pays off for large files spanning many
+    /// blocks; small files are better served by
+    /// [`try_read_file`](Self::try_read_file).
+    #[cfg(feature = "rayon")]
+    pub fn read_file_parallel(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        use rayon::prelude::*;
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        self.timed(
+            || {
+                let handle = self.file_handle(file)?;
+                let mut reader = self.0.write().unwrap();
+                let size = reader.pin_mut().GetFileSize(handle)?;
+                if size == 0 {
+                    return Ok(vec![]);
+                }
+                let data_offset = reader.GetFileDataOffset(handle)?;
+                let first = data_offset / BLOCK_SIZE as u64;
+                let last = (data_offset + size - 1) / BLOCK_SIZE as u64;
+                let extents = (first..=last)
+                    .map(|block| {
+                        Ok((
+                            reader.GetBlockPhysicalOffset(block)?,
+                            reader.GetBlockCompressedSize(block)? as u64,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                drop(reader);
+
+                // The lock is only held for one batch of blocks at a time, so
+                // other readers aren't shut out for the whole file.
+                let mut blocks = Vec::with_capacity(extents.len());
+                for batch in extents.chunks(PARALLEL_READ_BATCH) {
+                    let mut reader = self.0.write().unwrap();
+                    for &(offset, length) in batch {
+                        let mut raw = vec![0; length as usize];
+                        let read = unsafe {
+                            reader
+                                .pin_mut()
+                                .ReadArchiveBytes(offset, length, raw.as_mut_ptr())?
+                        };
+                        if read != length {
+                            return Err(ZArchiveError::ShortRead {
+                                expected: length,
+                                actual: read,
+                            });
+                        }
+                        blocks.push(raw);
+                    }
+                }
+
+                let mut data = vec![0; size as usize];
+                let mut targets = Vec::with_capacity(blocks.len());
+                let mut rest = &mut data[..];
+                let mut skip = (data_offset % BLOCK_SIZE as u64) as usize;
+                for _ in &blocks {
+                    let take = (BLOCK_SIZE - skip).min(rest.len());
+                    let (target, tail) = std::mem::take(&mut rest).split_at_mut(take);
+                    targets.push((skip, target));
+                    rest = tail;
+                    skip = 0;
+                }
+                blocks
+                    .into_par_iter()
+                    .zip(targets)
+                    .try_for_each(|(raw, (skip, target))| {
+                        if raw.len() == BLOCK_SIZE {
+                            target.copy_from_slice(&raw[skip..skip + target.len()]);
+                            return Ok(());
+                        }
+                        let mut block = vec![0u8; BLOCK_SIZE];
+                        let written = unsafe {
+                            zstd_sys::ZSTD_decompress(
+                                block.as_mut_ptr().cast(),
+                                BLOCK_SIZE,
+                                raw.as_ptr().cast(),
+                                raw.len(),
+                            )
+                        };
+                        if unsafe { zstd_sys::ZSTD_isError(written) } != 0 {
+                            let name = unsafe {
+                                std::ffi::CStr::from_ptr(zstd_sys::ZSTD_getErrorName(written))
+                            };
+                            return Err(ZArchiveError::DecompressionFailed {
+                                path: file.to_owned(),
+                                detail: name.to_string_lossy().into_owned(),
+                            });
+                        }
+                        if written != BLOCK_SIZE {
+                            return Err(ZArchiveError::DecompressionFailed {
+                                path: file.to_owned(),
+                                detail: format!("unexpected block size {written}"),
+                            });
+                        }
+                        target.copy_from_slice(&block[skip..skip + target.len()]);
+                        Ok(())
+                    })?;
+                Ok(data)
+            },
+            |data, duration| ReaderEvent::Read {
+                path: file,
+                bytes: data.as_ref().map_or(0, |data| data.len() as u64),
+                duration,
+            },
+        )
+    }
+
+    /// Read part of a file from the archive, clamping the range to the file
+    /// instead of failing. An offset past the end yields an empty `Vec`, and
+    /// a length running past the end is cut short.
+    pub fn read_from_file_clamped(
+        &self,
+        file: impl AsRef<Path>,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        let file = file.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(file.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self.file_handle(file)?;
+        let size = self.0.write().unwrap().pin_mut().GetFileSize(handle)?;
+        let start = offset.min(size);
+        let end = start + length.min(size - start);
+        let mut buffer = Vec::with_capacity((end - start) as usize);
+        self.copy_range_to(file, start..end, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Get a list of all the files in the archive (more convenient than manual
+    /// iteration if you can spare the allocation).
+    pub fn get_files(&self) -> Result<Vec<String>> {
+        Ok(self.get_paths()?.0)
+    }
+
+    /// Get a list of all the directories in the archive, including empty ones,
+    /// with each parent listed before its children, so they can be created in
+    /// order. The root itself is not included.
+    pub fn get_dirs(&self) -> Result<Vec<String>> {
+        Ok(self.get_paths()?.1)
+    }
+
+    /// Collect the full paths of every file and every directory in the
+    /// archive in a single walk.
+    fn get_paths(&self) -> Result<(Vec<String>, Vec<String>)> {
+        fn process_dir_entry(
+            archive: &ZArchiveReader,
+            files: &mut Vec<String>,
+            dirs: &mut Vec<String>,
+            node_handle: ZArchiveNodeHandle,
+            parent: &str,
+            dir_entry: &mut ffi::DirEntry,
+        ) -> Result<()> {
+            let count = archive.0.read().unwrap().GetDirEntryCount(node_handle)?;
+            for i in 0..count {
+                if archive
+                    .0
+                    .read()
+                    .unwrap()
+                    .GetDirEntry(node_handle, i, dir_entry)?
+                {
+                    let full_path = if !parent.is_empty() {
+                        [parent, dir_entry.name].join("/")
+                    } else {
+                        dir_entry.name.to_owned()
+                    };
+                    if dir_entry.isFile {
+                        files.push(full_path);
+                    } else if dir_entry.isDirectory {
+                        let next = archive
+                            .0
+                            .write()
+                            .unwrap()
+                            .pin_mut()
+                            .LookUp(&full_path, false, true)?;
+                        dirs.push(full_path.clone());
+                        if next != ZARCHIVE_INVALID_NODE {
+                            process_dir_entry(archive, files, dirs, next, &full_path, dir_entry)?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        let mut dir_entry = ffi::DirEntry::default();
+        let (mut files, mut dirs) = (vec![], vec![]);
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root != ZARCHIVE_INVALID_NODE {
+            process_dir_entry(self, &mut files, &mut dirs, root, "", &mut dir_entry)?;
+        }
+        Ok((files, dirs))
+    }
+
+    /// Count the files and directories in the subtree below a directory node,
+    /// recursively, reusing `path` as scratch space for lookups.
+    fn count_subtree(
+        &self,
+        node_handle: ZArchiveNodeHandle,
+        path: &mut String,
+        dir_entry: &mut ffi::DirEntry,
+    ) -> Result<(usize, usize)> {
+        let (mut files, mut dirs) = (0, 0);
+        let count = self.0.read().unwrap().GetDirEntryCount(node_handle)?;
+        for i in 0..count {
+            if self
+                .0
+                .read()
+                .unwrap()
+                .GetDirEntry(node_handle, i, dir_entry)?
+            {
+                if dir_entry.isFile {
+                    files += 1;
+                } else if dir_entry.isDirectory {
+                    dirs += 1;
+                    let len = path.len();
+                    if !path.is_empty() {
+                        path.push('/');
+                    }
+                    path.push_str(dir_entry.name);
+                    let next = self
+                        .0
+                        .write()
+                        .unwrap()
+                        .pin_mut()
+                        .LookUp(path, false, true)?;
+                    if next != ZARCHIVE_INVALID_NODE {
+                        let (sub_files, sub_dirs) = self.count_subtree(next, path, dir_entry)?;
+                        files += sub_files;
+                        dirs += sub_dirs;
+                    }
+                    path.truncate(len);
+                }
+            }
+        }
+        Ok((files, dirs))
+    }
+
+    fn check_duplicates(
+        &self,
+        node_handle: ZArchiveNodeHandle,
+        path: &mut String,
+        dir_entry: &mut ffi::DirEntry,
+    ) -> Result<()> {
+        let count = self.0.read().unwrap().GetDirEntryCount(node_handle)?;
+        let mut seen = std::collections::HashSet::with_capacity(count as usize);
+        for i in 0..count {
+            if !self
+                .0
+                .read()
+                .unwrap()
+                .GetDirEntry(node_handle, i, dir_entry)?
+            {
+                continue;
+            }
+            if !seen.insert(dir_entry.name.to_ascii_lowercase()) {
+                return Err(ZArchiveError::DuplicateEntry {
+                    parent: path.clone(),
+                    name: dir_entry.name.to_owned(),
+                });
+            }
+            if dir_entry.isDirectory {
+                let len = path.len();
+                if !path.is_empty() {
+                    path.push('/');
+                }
+                path.push_str(dir_entry.name);
+                let next = self
+                    .0
+                    .write()
+                    .unwrap()
+                    .pin_mut()
+                    .LookUp(path, false, true)?;
+                if next != ZARCHIVE_INVALID_NODE {
+                    self.check_duplicates(next, path, dir_entry)?;
+                }
+                path.truncate(len);
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the files under a directory in the archive, recursively, without
+    /// collecting their paths. If the prefix is a file, the count is 1.
+    pub fn file_count_under(&self, prefix: impl AsRef<Path>) -> Result<usize> {
+        let prefix = prefix.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(prefix.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self
+            .0
+            .write()
+            .unwrap()
+            .pin_mut()
+            .LookUp(prefix, true, true)?;
+        if handle == ZARCHIVE_INVALID_NODE {
+            Err(ZArchiveError::MissingFile(prefix.to_owned()))
+        } else if self.0.read().unwrap().IsFile(handle)? {
+            Ok(1)
+        } else {
+            let mut path = prefix.trim_matches(&['/', '\\'][..]).to_owned();
+            self.count_subtree(handle, &mut path, &mut ffi::DirEntry::default())
+                .map(|(files, _)| files)
+        }
+    }
+
+    /// Count the directories in the archive, recursively, not including the
+    /// root itself.
+    pub fn dir_count(&self) -> Result<usize> {
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile("archive root".to_owned()));
+        }
+        self.count_subtree(root, &mut String::new(), &mut ffi::DirEntry::default())
+            .map(|(_, dirs)| dirs)
+    }
+
+    /// Find the longest directory prefix shared by every file in the archive,
+    /// e.g. `content` if all files are somewhere under `content/`. Returns
+    /// `None` if the files have no common directory.
+    pub fn common_prefix(&self) -> Result<Option<String>> {
+        let mut prefix: Option<String> = None;
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if !entry.is_file() {
+                continue;
+            }
+            let dir = entry
+                .full_path()
+                .rsplit_once('/')
+                .map(|(dir, _)| dir)
+                .unwrap_or("");
+            match prefix.as_mut() {
+                None => prefix = Some(dir.to_owned()),
+                Some(prefix) => {
+                    let mut len = 0;
+                    for (a, b) in prefix.split('/').zip(dir.split('/')) {
+                        if a != b {
+                            break;
+                        }
+                        len += a.len() + 1;
+                    }
+                    prefix.truncate(len.saturating_sub(1));
+                }
+            }
+            if prefix.as_deref() == Some("") {
+                break;
+            }
+        }
+        Ok(prefix.filter(|prefix| !prefix.is_empty()))
+    }
+
+    /// List the files in the archive in the order their data is stored, so a
+    /// pass reading every file in this order moves through the archive
+    /// sequentially instead of seeking back and forth.
+    pub fn files_in_offset_order(&self) -> Result<Vec<String>> {
+        let mut files = self
+            .get_files()?
+            .into_iter()
+            .map(|file| {
+                let handle = self.file_handle(&file)?;
+                let offset = self.0.read().unwrap().GetFileDataOffset(handle)?;
+                Ok((offset, file))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        files.sort_by_key(|(offset, _)| *offset);
+        Ok(files.into_iter().map(|(_, file)| file).collect())
+    }
+
+    /// Collect every entry in the archive, files and directories alike, for
+    /// which `pred` returns true.
+    pub fn find_all(&self, pred: impl Fn(&DirEntry) -> bool) -> Result<Vec<OwnedDirEntry>> {
+        let mut found = Vec::new();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if pred(&entry.as_dir_entry()) {
+                found.push(entry);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Collect every file in the archive sorted by size, largest first if
+    /// `descending` is set. Sizes come from the directory entries gathered
+    /// during the walk, so no per-file lookups are needed.
+    pub fn files_by_size(&self, descending: bool) -> Result<Vec<OwnedDirEntry>> {
+        let mut files = Vec::new();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                files.push(entry);
+            }
+        }
+        if descending {
+            files.sort_by_key(|entry| std::cmp::Reverse(entry.size));
+        } else {
+            files.sort_by_key(|entry| entry.size);
+        }
+        Ok(files)
+    }
+
+    /// Count the files in the archive by lowercased extension. Files with no
+    /// extension are counted under an empty key.
+    pub fn extension_counts(&self) -> Result<std::collections::HashMap<String, usize>> {
+        let mut counts = std::collections::HashMap::new();
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                let ext = Path::new(entry.name())
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                *counts.entry(ext).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Resolve and size every file listed in the archive, returning the paths
+    /// that fail along with the error each produced. An empty list means every
+    /// listed file can be looked up.
+    pub fn validate_paths(&self) -> Result<Vec<(String, ZArchiveError)>> {
+        Ok(self
+            .get_files()?
+            .into_iter()
+            .filter_map(|file| {
+                self.file_handle(&file)
+                    .and_then(|handle| {
+                        self.0
+                            .write()
+                            .unwrap()
+                            .pin_mut()
+                            .GetFileSize(handle)
+                            .map_err(ZArchiveError::from)
+                    })
+                    .err()
+                    .map(|e| (file, e))
+            })
+            .collect())
+    }
+
+    /// Recursively iterate over every entry in the archive after first counting
+    /// them, returning the count along with a lazy iterator. The count pass
+    /// doesn't collect any paths, so this is a cheap way to drive a progress
+    /// bar while streaming entries.
+    pub fn walk_counted(&self) -> Result<(usize, WalkCounted<'_>)> {
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile("archive root".to_owned()));
+        }
+        let (files, dirs) =
+            self.count_subtree(root, &mut String::new(), &mut ffi::DirEntry::default())?;
+        Ok((
+            files + dirs,
+            WalkCounted {
+                inner: WalkWithDepth::new(self, root, String::new())?,
+                remaining: files + dirs,
+            },
+        ))
+    }
+
+    /// Iterate over the contents of the root directory of the archive.
+    pub fn iter(&self) -> Result<ArchiveDirIterator<'_>> {
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root == ZARCHIVE_INVALID_NODE {
+            Err(ZArchiveError::MissingFile("archive root".to_owned()))
+        } else {
+            Ok(ArchiveDirIterator::new(root, smallvec![], self))
+        }
+    }
+
+    /// Recursively iterate over every entry in the archive, depth-first. Unlike
+    /// [`get_files`](Self::get_files) nothing is collected up front, so
+    /// stopping early or filtering costs only the entries actually visited.
+    pub fn walk(&self) -> Result<WalkIter<'_>> {
+        Ok(WalkIter {
+            inner: self.walk_with_depth()?,
+        })
+    }
+
+    /// Recursively iterate over every entry in the archive, depth-first, along
+    /// with the depth of each entry (0 for entries in the archive root). Useful
+    /// for rendering an indented tree.
+    pub fn walk_with_depth(&self) -> Result<WalkWithDepth<'_>> {
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root == ZARCHIVE_INVALID_NODE {
+            Err(ZArchiveError::MissingFile("archive root".to_owned()))
+        } else {
+            WalkWithDepth::new(self, root, String::new())
+        }
+    }
+
+    /// Recursively iterate over every entry in the archive, depth-first, with
+    /// the children of each directory sorted by name. The order is the same
+    /// on every run and platform, and only the open directories' children are
+    /// held in memory at once.
+    pub fn walk_sorted(&self) -> Result<impl Iterator<Item = Result<OwnedDirEntry>> + '_> {
+        let root = self.0.write().unwrap().pin_mut().LookUp("", false, true)?;
+        if root == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile("archive root".to_owned()));
+        }
+        Ok(WalkSorted {
+            reader: self,
+            stack: vec![WalkSorted::children(self, root, "")?],
+        })
+    }
+
+    /// Write the archive's file listing as newline-delimited JSON, one
+    /// `{"path":...,"size":...}` object per file, as the archive is walked.
+    /// Nothing is collected in memory, so this suits very large archives.
+    pub fn write_listing_ndjson(&self, w: &mut impl Write) -> Result<()> {
+        for entry in self.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if entry.is_file() {
+                w.write_all(b"{\"path\":")?;
+                write_json_string(w, entry.full_path())?;
+                writeln!(w, ",\"size\":{}}}", entry.size)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether two archives have the same tree: the same paths, each
+    /// being a file in both or a directory in both. Sizes and contents are
+    /// ignored.
+    pub fn same_structure(&self, other: &ZArchiveReader) -> Result<bool> {
+        let mut ours = self.walk_sorted()?;
+        let mut theirs = other.walk_sorted()?;
+        loop {
+            match (ours.next().transpose()?, theirs.next().transpose()?) {
+                (None, None) => return Ok(true),
+                (Some(a), Some(b))
+                    if a.is_file() == b.is_file() && a.full_path() == b.full_path() => {}
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    /// Recursively iterate over every file in the archive, descending into
+    /// files which `openers` recognizes as containers. Each file is yielded
+    /// with its path and size, and files inside a container get composite
+    /// paths like `outer.pack!/inner/file`, nesting as deep as containers do.
+    /// Containers are read whole to be opened.
+    pub fn walk_nested<'a>(&'a self, openers: &'a NestedOpeners) -> Result<NestedWalk<'a>> {
+        NestedWalk::new(self, openers)
+    }
+
+    /// Recursively iterate over the entries in the archive, depth-first, but
+    /// without descending below `max` levels (so a `max` of 0 yields only the
+    /// top-level entries). Non-empty directories at the limit are flagged by
+    /// [`OwnedDirEntry::has_unexpanded_children`].
+    pub fn walk_max_depth(
+        &self,
+        max: usize,
+    ) -> Result<impl Iterator<Item = Result<OwnedDirEntry>> + '_> {
+        let mut walk = self.walk_with_depth()?;
+        walk.max_depth = Some(max);
+        Ok(walk.map(|entry| entry.map(|(_, entry)| entry)))
+    }
+
+    /// Recursively iterate over the entries in the archive whose paths match a
+    /// glob pattern, lazily. Patterns support `*` and `?` within a segment and
+    /// `**` for any number of segments. The walk starts at the deepest
+    /// directory named literally by the pattern, so `content/Actor/**` never
+    /// descends into anything outside `content/Actor`.
+    pub fn walk_matching(
+        &self,
+        pattern: &str,
+    ) -> Result<impl Iterator<Item = Result<OwnedDirEntry>> + '_> {
+        let pattern: Vec<String> = pattern
+            .split(&['/', '\\'][..])
+            .filter(|seg| !seg.is_empty())
+            .map(str::to_owned)
+            .collect();
+        let literal = pattern
+            .iter()
+            .take_while(|seg| !seg.contains(&['*', '?'][..]))
+            .count()
+            .min(pattern.len().saturating_sub(1));
+        let prefix = pattern[..literal].join("/");
+        let mut reader = self.0.write().unwrap();
+        let handle = reader.pin_mut().LookUp(&prefix, false, true)?;
+        let walk = if handle == ZARCHIVE_INVALID_NODE || reader.IsFile(handle)? {
+            None
+        } else {
+            drop(reader);
+            Some(WalkWithDepth::new(self, handle, prefix)?)
+        };
+        Ok(walk
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.map(|(_, entry)| entry))
+            .filter(move |entry| match entry {
+                Ok(entry) => {
+                    let path: Vec<&str> = entry.full_path().split('/').collect();
+                    glob_matches(&pattern, &path)
+                }
+                Err(_) => true,
+            }))
+    }
+
+    /// Get a window of up to `limit` entries of a directory in the archive,
+    /// starting at entry `offset`. Entries are addressed by index, so earlier
+    /// entries are never visited.
+    pub fn dir_page(
+        &self,
+        path: impl AsRef<Path>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<OwnedDirEntry>> {
+        let path = path.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(path.as_ref().to_string_lossy().to_string())
+        })?;
+        let handle = self
+            .0
+            .write()
+            .unwrap()
+            .pin_mut()
+            .LookUp(path, false, true)?;
+        let reader = self.0.read().unwrap();
+        if handle == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile(path.to_owned()));
+        } else if reader.IsFile(handle)? {
+            return Err(ZArchiveError::NotADirectory(path.to_owned()));
+        }
+        let parent = path.trim_matches(&['/', '\\'][..]);
+        let count = reader.GetDirEntryCount(handle)?;
+        let end = offset.saturating_add(limit).min(count);
+        let mut dir_entry = ffi::DirEntry::default();
+        let mut entries = Vec::with_capacity(end.saturating_sub(offset) as usize);
+        for i in offset..end {
+            if reader.GetDirEntry(handle, i, &mut dir_entry)? {
+                entries.push(OwnedDirEntry::new(parent, &dir_entry));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// List the children of a directory in the archive as plain owned
+    /// `(name, is_file, size)` tuples, with no size for directories. The
+    /// lookup and every entry are read under a single lock.
+    pub fn entry_tuples(&self, path: impl AsRef<Path>) -> Result<Vec<(String, bool, Option<u64>)>> {
+        let path = path.as_ref().to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(path.as_ref().to_string_lossy().to_string())
+        })?;
+        let mut reader = self.0.write().unwrap();
+        let handle = reader.pin_mut().LookUp(path, false, true)?;
+        if handle == ZARCHIVE_INVALID_NODE {
+            return Err(ZArchiveError::MissingFile(path.to_owned()));
+        } else if reader.IsFile(handle)? {
+            return Err(ZArchiveError::NotADirectory(path.to_owned()));
+        }
+        let count = reader.GetDirEntryCount(handle)?;
+        let mut dir_entry = ffi::DirEntry::default();
+        let mut entries = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            if reader.GetDirEntry(handle, i, &mut dir_entry)? {
+                entries.push((
+                    dir_entry.name.to_owned(),
+                    dir_entry.isFile,
+                    dir_entry.isFile.then_some(dir_entry.size),
+                ));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Get the entry at `index` in a directory in the archive, or `None` if the
+    /// directory has no more than `index` entries. The entry is addressed
+    /// directly, without visiting the ones before it.
+    pub fn entry_at(&self, path: impl AsRef<Path>, index: u32) -> Result<Option<OwnedDirEntry>> {
+        Ok(self.dir_page(path, index, 1)?.pop())
+    }
+
+    /// Iterate over the contents of a directory in the archive.
+    pub fn iter_dir<'a, 'entry>(
+        &'a self,
+        dir: &'entry DirEntry<'a>,
+    ) -> Result<ArchiveDirIterator<'entry>>
+    where
+        'a: 'entry,
+    {
+        let node_handle =
+            self.0
+                .write()
+                .unwrap()
+                .pin_mut()
+                .LookUp(&dir.full_path(), false, true)?;
+        if node_handle == ZARCHIVE_INVALID_NODE {
+            Err(ZArchiveError::MissingFile(dir.full_path()))
+        } else if !dir.is_dir() {
+            Err(ZArchiveError::NotADirectory(dir.full_path()))
+        } else {
+            Ok(ArchiveDirIterator::new(
+                node_handle,
+                dir.parent.iter().copied().chain([dir.name()]).collect(),
+                self,
+            ))
+        }
+    }
+
+    /// Count the contents of a directory in the archive.
+    pub fn count_dir_entries<'a>(&'a self, dir: &'a DirEntry) -> Result<usize> {
+        let mut reader = self.0.write().unwrap();
+        let node_handle = reader.pin_mut().LookUp(&dir.full_path(), false, true)?;
+        if node_handle == ZARCHIVE_INVALID_NODE {
+            Err(ZArchiveError::MissingFile(dir.full_path()))
+        } else if !dir.is_dir() {
+            Err(ZArchiveError::NotADirectory(dir.full_path()))
+        } else {
+            Ok(reader.pin_mut().GetDirEntryCount(node_handle)? as usize)
+        }
+    }
+}
+
+#[allow(clippy::missing_safety_doc)]
+#[cxx::bridge]
+mod ffi {
+    #[derive(Debug, Default, Clone)]
+    #[allow(non_snake_case)]
+    struct DirEntry<'a> {
+        name: &'a str,
+        isFile: bool,
+        isDirectory: bool,
+        size: u64,
+    }
+
+    /// What kind of failure made the last read from a file throw.
+    #[derive(Debug)]
+    enum ReadErrorKind {
+        None,
+        Io,
+        Corrupt,
+        Decompression,
+    }
+
+    extern "Rust" {
+        type ReadSource;
+        fn read_at(self: &mut ReadSource, offset: u64, buf: &mut [u8]) -> bool;
+    }
+
+    unsafe extern "C++" {
+        include!("zarchive/include/zarchive/zarchivereader.h");
+
+        type ZArchiveNodeHandle = super::ZArchiveNodeHandle;
+        type ZArchiveReader;
+        fn OpenFromFile(path: &str) -> Result<UniquePtr<ZArchiveReader>>;
+        fn OpenFromMemory(data: Vec<u8>) -> Result<UniquePtr<ZArchiveReader>>;
+        fn OpenFromSource(source: Box<ReadSource>, size: u64) -> Result<UniquePtr<ZArchiveReader>>;
+        fn OpenFromFileRange(
+            path: &str,
+            offset: u64,
+            size: u64,
+        ) -> Result<UniquePtr<ZArchiveReader>>;
+        fn LookUp(
+            self: Pin<&mut ZArchiveReader>,
+            path: &str,
+            allowFile: bool,
+            allowDirectory: bool,
+        ) -> Result<ZArchiveNodeHandle>;
+        fn LookUpSegments(
+            self: Pin<&mut ZArchiveReader>,
+            segments: &[&str],
+        ) -> Result<ZArchiveNodeHandle>;
+        #[allow(unused)]
+        fn IsDirectory(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<bool>;
+        fn IsFile(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<bool>;
+        fn GetDirEntryCount(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<u32>;
+        fn GetDirEntry<'a>(
+            self: &'a ZArchiveReader,
+            nodeHandle: ZArchiveNodeHandle,
+            index: u32,
+            dirEntry: &'a mut DirEntry,
+        ) -> Result<bool>;
+        fn GetFileSize(
+            self: Pin<&mut ZArchiveReader>,
+            nodeHandle: ZArchiveNodeHandle,
+        ) -> Result<u64>;
+        unsafe fn ReadFromFile(
+            self: Pin<&mut ZArchiveReader>,
+            nodeHandle: ZArchiveNodeHandle,
+            offset: u64,
+            size: u64,
+            buffer: *mut u8,
+        ) -> Result<u64>;
+        fn GetLastErrorKind(self: &ZArchiveReader) -> ReadErrorKind;
+        fn GetFileDataOffset(self: &ZArchiveReader, nodeHandle: ZArchiveNodeHandle) -> Result<u64>;
+        fn GetBlockPhysicalOffset(self: &ZArchiveReader, blockIndex: u64) -> Result<u64>;
+        fn GetBlockCompressedSize(self: &ZArchiveReader, blockIndex: u64) -> Result<u32>;
+        fn GetVersion(self: &ZArchiveReader) -> u32;
+        fn GetBlockCount(self: &ZArchiveReader) -> u64;
+        fn GetOffsetRecordCount(self: &ZArchiveReader) -> u64;
+        fn GetCompressedDataSize(self: &ZArchiveReader) -> u64;
+        fn GetFileCount(self: &ZArchiveReader) -> u64;
+        fn GetArchiveSize(self: Pin<&mut ZArchiveReader>) -> Result<u64>;
+        fn SetKeepOpen(self: Pin<&mut ZArchiveReader>, keepOpen: bool) -> Result<()>;
+        fn Reopen(self: Pin<&mut ZArchiveReader>) -> Result<UniquePtr<ZArchiveReader>>;
+        fn SetRetryPolicy(
+            self: Pin<&mut ZArchiveReader>,
+            attempts: u32,
+            backoffUs: u64,
+        ) -> Result<()>;
+        unsafe fn ReadArchiveBytes(
+            self: Pin<&mut ZArchiveReader>,
+            offset: u64,
+            length: u64,
+            buffer: *mut u8,
+        ) -> Result<u64>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Build an archive in a new temporary directory from `(path, contents)`
+    /// pairs. A path ending in `/` is created as an empty directory instead.
+    fn build_archive(files: &[(&str, &[u8])]) -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.zar");
+        let mut writer = crate::ZArchiveWriter::new(&path).unwrap();
+        for (file, data) in files {
+            match file.strip_suffix('/') {
+                Some(dir) => writer.make_dir(dir, true).unwrap(),
+                None => {
+                    writer.start_file(file).unwrap();
+                    writer.append_data(data).unwrap();
+                }
+            }
+        }
+        writer.finalize().unwrap();
+        (temp_dir, path)
+    }
+
+    /// Pseudo-random bytes, which zstd can't compress.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545f4914f6cdd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file_list() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        for file in archive.get_files().unwrap() {
+            println!("{}", file);
+        }
+    }
+
+    #[test]
+    fn count_under() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.file_count_under("content/Model").unwrap(), 24);
+        assert_eq!(archive.file_count_under("rules.txt").unwrap(), 1);
+        assert_eq!(
+            archive.file_count_under("").unwrap(),
+            archive.get_files().unwrap().len()
+        );
+        assert!(archive.file_count_under("content/Nothing").is_err());
+    }
+
+    #[test]
+    fn listing_ndjson() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut out = Vec::new();
+        archive.write_listing_ndjson(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), archive.get_files().unwrap().len());
+        assert!(out
+            .lines()
+            .any(|line| line == r#"{"path":"rules.txt","size":300}"#));
+
+        let mut escaped = Vec::new();
+        write_json_string(&mut escaped, "a\"b\\c\nd\u{1}é").unwrap();
+        assert_eq!(
+            String::from_utf8(escaped).unwrap(),
+            r#""a\"b\\c\nd\u0001é""#
+        );
+    }
+
+    #[test]
+    fn files_by_size() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let files = archive.files_by_size(true).unwrap();
+        assert_eq!(files.len(), archive.get_files().unwrap().len());
+        assert!(files.windows(2).all(|w| w[0].size() >= w[1].size()));
+        let files = archive.files_by_size(false).unwrap();
+        assert_eq!(files[0].size(), Some(0));
+    }
+
+    #[test]
+    fn extension_counts() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let counts = archive.extension_counts().unwrap();
+        assert_eq!(
+            counts.values().sum::<usize>(),
+            archive.get_files().unwrap().len()
+        );
+        assert!(counts["txt"] >= 1);
+        assert!(counts["sbfres"] > 0);
+        assert!(counts["pack"] > 0);
+    }
+
+    #[test]
+    fn header() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let header = archive.header().unwrap();
+        assert_eq!(header.version, 1);
+        assert_eq!(header.block_size, 64 * 1024);
+        assert_eq!(header.block_count, header.offset_record_count * 16);
+        assert_eq!(
+            header.file_count as usize,
+            archive.get_files().unwrap().len()
+        );
+        assert!(header.compressed_data_size > 0);
+    }
+
+    #[test]
+    fn entry_tuples() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let tuples = archive.entry_tuples("").unwrap();
+        assert!(tuples.contains(&("rules.txt".to_owned(), true, Some(300))));
+        assert!(tuples.contains(&("content".to_owned(), false, None)));
+        let page = archive.dir_page("content/Model", 0, u32::MAX).unwrap();
+        let tuples = archive.entry_tuples("content/Model").unwrap();
+        assert_eq!(tuples.len(), page.len());
+        assert!(tuples
+            .iter()
+            .zip(&page)
+            .all(|((name, is_file, size), entry)| name == entry.name()
+                && *is_file == entry.is_file()
+                && size.map(|s| s as usize) == entry.size()));
+        assert!(matches!(
+            archive.entry_tuples("rules.txt"),
+            Err(ZArchiveError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn entry_at() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let all = archive.dir_page("content/Model", 0, u32::MAX).unwrap();
+        let entry = archive.entry_at("content/Model", 5).unwrap().unwrap();
+        assert_eq!(entry.full_path(), all[5].full_path());
+        assert!(archive
+            .entry_at("content/Model", all.len() as u32)
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            archive.entry_at("rules.txt", 0),
+            Err(ZArchiveError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn dir_page() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let all = archive.dir_page("content/Model", 0, u32::MAX).unwrap();
+        let page = archive.dir_page("content/Model", 20, 10).unwrap();
+        assert_eq!(page.len(), all.len().saturating_sub(20).min(10));
+        assert_eq!(page[0].full_path(), all[20].full_path());
+        assert!(archive
+            .dir_page("content/Model", all.len() as u32, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(archive.dir_page("", 0, 2).unwrap().len(), 2);
+        assert!(archive.dir_page("rules.txt", 0, 1).is_err());
+    }
+
+    #[test]
+    fn common_prefix() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.common_prefix().unwrap(), None);
+
+        let (_temp_dir, path) = build_archive(&[
+            ("root/sub/a.txt", b"a"),
+            ("root/sub/deeper/b.txt", b"b"),
+            ("root/subway/c.txt", b"c"),
+        ]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert_eq!(archive.common_prefix().unwrap().as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn sizes() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let (size, compressed) = archive.sizes("content/Pack/Bootup.pack").unwrap();
+        assert_eq!(size, 29021400);
+        let ranges = archive
+            .physical_range("content/Pack/Bootup.pack", 0..size)
+            .unwrap();
+        assert_eq!(
+            compressed,
+            ranges
+                .iter()
+                .map(|range| range.end - range.start)
+                .sum::<u64>()
+        );
+        assert!(compressed < size);
+        assert_eq!(
+            archive.sizes("aoc/0010/Pack/AocMainField.pack").unwrap(),
+            (0, 0)
+        );
+        assert!(archive.sizes("missing.txt").is_err());
+    }
+
+    #[test]
+    fn is_compressed() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert!(archive.is_compressed("rules.txt").unwrap());
+        assert!(matches!(
+            archive.is_compressed("content"),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+
+        let noise = noise(BLOCK_SIZE * 2);
+        let (_temp_dir, path) = build_archive(&[("noise.bin", &noise), ("empty.bin", b"")]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert!(!archive.is_compressed("noise.bin").unwrap());
+        assert_eq!(
+            archive.sizes("noise.bin").unwrap(),
+            (BLOCK_SIZE as u64 * 2, BLOCK_SIZE as u64 * 2)
+        );
+        assert_eq!(archive.read_file("noise.bin").unwrap(), noise);
+        assert!(!archive.is_compressed("empty.bin").unwrap());
+    }
+
+    #[test]
+    fn physical_range() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let archive_size = std::fs::metadata("test/crafting.zar").unwrap().len();
+        let ranges = archive
+            .physical_range("content/Pack/Bootup.pack", 0..29021400)
+            .unwrap();
+        assert!(!ranges.is_empty());
+        assert!(ranges
+            .iter()
+            .all(|range| range.start < range.end && range.end <= archive_size));
+        let header = archive
+            .physical_range("content/Pack/Bootup.pack", 0..4)
+            .unwrap();
+        assert_eq!(header.len(), 1);
+        assert!(archive
+            .physical_range("content/Pack/Bootup.pack", 0..0)
+            .unwrap()
+            .is_empty());
+        assert!(archive.physical_range("rules.txt", 0..301).is_err());
+    }
+
+    #[test]
+    fn offset_order() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let ordered = archive.files_in_offset_order().unwrap();
+        let mut files = archive.get_files().unwrap();
+        assert_eq!(ordered.len(), files.len());
+        let mut sorted = ordered.clone();
+        sorted.sort();
+        files.sort();
+        assert_eq!(sorted, files);
+        let ranges: Vec<_> = ordered
+            .iter()
+            .filter(|file| archive.file_size(file) != Some(0))
+            .map(|file| archive.physical_range(file, 0..1).unwrap()[0].start)
+            .collect();
+        assert!(ranges.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn get_dirs() {
+        let (_temp_dir, path) = build_archive(&[("empty/nested/", b""), ("full/a.txt", b"a")]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        let mut dirs = archive.get_dirs().unwrap();
+        let nested = dirs.iter().position(|d| d == "empty/nested").unwrap();
+        assert!(dirs.iter().position(|d| d == "empty").unwrap() < nested);
+        dirs.sort();
+        assert_eq!(dirs, ["empty", "empty/nested", "full"]);
+        assert_eq!(archive.get_files().unwrap(), ["full/a.txt"]);
+    }
+
+    #[test]
+    fn empty_dirs() {
+        let (temp_dir, path) = build_archive(&[("empty/nested/", b""), ("full/a.txt", b"a")]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert_eq!(
+            archive.empty_dirs().unwrap(),
+            vec!["empty/nested".to_owned()]
+        );
+
+        let out = temp_dir.path().join("plain");
+        archive.extract(&out).unwrap();
+        assert!(!out.join("empty").exists());
+        let out = temp_dir.path().join("full");
+        archive
+            .extract_with_options(
+                &out,
+                ExtractOptions {
+                    create_empty_dirs: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(out.join("empty/nested").is_dir());
+        assert!(out.join("full/a.txt").is_file());
+        let out = temp_dir.path().join("progress");
+        let mut reports = 0;
+        archive
+            .extract_with_progress(&out, |_| reports += 1)
+            .unwrap();
+        assert_eq!(reports, 1);
+        assert!(out.join("empty/nested").is_dir());
+        assert!(out.join("full/a.txt").is_file());
+    }
+
+    #[test]
+    fn find_all() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let big = archive
+            .find_all(|entry| entry.size().is_some_and(|size| size > 1_000_000))
+            .unwrap();
+        assert!(big
+            .iter()
+            .any(|entry| entry.full_path() == "content/Pack/Bootup.pack"));
+        assert!(big.iter().all(|entry| entry.is_file()));
+        let models = archive
+            .find_all(|entry| entry.is_dir() && entry.name() == "Model")
+            .unwrap();
+        assert!(models
+            .iter()
+            .any(|entry| entry.full_path() == "content/Model"));
+        let nested = archive
+            .find_all(|entry| entry.full_path() == "content/Model/Item_Feather.sbfres")
+            .unwrap();
+        assert_eq!(nested.len(), 1);
+    }
+
+    #[test]
+    fn dir_count() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let (count, walk) = archive.walk_counted().unwrap();
+        let dirs = walk
+            .filter(|entry| entry.as_ref().unwrap().is_dir())
+            .count();
+        assert_eq!(archive.dir_count().unwrap(), dirs);
+        assert_eq!(
+            archive.dir_count().unwrap() + archive.get_files().unwrap().len(),
+            count
+        );
+    }
+
+    #[test]
+    fn same_structure() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert!(archive.same_structure(&archive).unwrap());
+
+        let open = |files: &[(&str, &[u8])]| {
+            let (temp_dir, path) = build_archive(files);
+            (temp_dir, ZArchiveReader::open(path).unwrap())
+        };
+        let (_a, a) = open(&[("e/", b""), ("x/a.txt", b"a"), ("b.txt", b"b")]);
+        let (_b, b) = open(&[("e/", b""), ("b.txt", b"bbbb"), ("x/a.txt", b"")]);
+        let (_c, c) = open(&[("x/a.txt", b"a"), ("b.txt", b"b")]);
+        let (_d, d) = open(&[("e/f/", b""), ("x/a.txt", b"a"), ("b.txt", b"b")]);
+        let (_e, e) = open(&[("b.txt/", b""), ("x/a.txt", b"a"), ("e", b"b")]);
+        assert!(a.same_structure(&b).unwrap());
+        assert!(!a.same_structure(&c).unwrap());
+        assert!(!a.same_structure(&d).unwrap());
+        assert!(!a.same_structure(&e).unwrap());
+    }
+
+    #[test]
+    fn same_node() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert!(archive.same_node("rules.txt", "RULES.TXT").unwrap());
+        assert!(archive
+            .same_node("content/Model", "/content//model/")
+            .unwrap());
+        assert!(!archive.same_node("rules.txt", "content").unwrap());
+        assert!(archive.same_node("rules.txt", "nothing.txt").is_err());
+    }
+
+    #[test]
+    fn validate_paths() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert!(archive.validate_paths().unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_references() {
+        let (_temp_dir, path) = build_archive(&[
+            ("list.txt", b"a.txt\nmissing.txt\nDIR/B.TXT\ndir"),
+            ("a.txt", b"a"),
+            ("dir/b.txt", b"b"),
+        ]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        let dangling = archive
+            .check_references(|file, data| {
+                if file.starts_with("list") {
+                    std::str::from_utf8(data)
+                        .unwrap()
+                        .lines()
+                        .map(str::to_owned)
+                        .collect()
+                } else {
+                    vec![]
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            dangling,
+            vec![
+                ("list.txt".to_owned(), "missing.txt".to_owned()),
+                ("list.txt".to_owned(), "dir".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn path_segments() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(
+            archive
+                .read_path_segments(&["content", "Model", "Item_Feather.sbfres"])
+                .unwrap(),
+            archive.read_file("content/Model/Item_Feather.sbfres")
+        );
+        assert!(matches!(
+            archive.read_path_segments(&["content", "Model"]),
+            Err(ZArchiveError::NotAFile(path)) if path == "content/Model"
+        ));
+        assert!(archive
+            .read_path_segments(&["content", "Nothing"])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn directory_as_file() {
+        fn not_a_file<T>(result: Result<T>) -> bool {
+            matches!(result, Err(ZArchiveError::NotAFile(_)))
+        }
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let dir = "content/Model";
+        assert!(archive.file_size(dir).is_none());
+        assert!(archive.read_file(dir).is_none());
+        assert!(archive.read_from_file(dir, 0, 0).is_none());
+        assert!(not_a_file(archive.read_file_or_empty(dir)));
+        assert!(not_a_file(
+            archive.read_path_segments(&["content", "Model"])
+        ));
+        let local = ZArchiveReader::open_unsync("test/crafting.zar").unwrap();
+        assert!(local.file_size(dir).is_none());
+        assert!(local.read_file(dir).is_none());
+        assert!(local.read_from_file(dir, 0, 0).is_none());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("out");
+        assert!(not_a_file(archive.extract_file_to(dir, &dest)));
+        assert!(not_a_file(archive.extract_file_into(dir, temp_dir.path())));
+        assert!(not_a_file(archive.with_read_buffer(dir, |_| ())));
+        assert!(not_a_file(
+            archive.read_file_decoded(dir, &DecoderRegistry::new())
+        ));
+        assert!(not_a_file(archive.copy_range_to(
+            dir,
+            0..0,
+            &mut std::io::sink()
+        )));
+        assert!(not_a_file(archive.read_from_file_clamped(dir, 0, 1)));
+        assert!(not_a_file(archive.physical_range(dir, 0..0)));
+        std::fs::write(&dest, b"").unwrap();
+        assert!(not_a_file(archive.verify_extracted(dir, &dest)));
+        assert!(matches!(
+            archive.extract_file_to("content/Nothing", &dest),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+    }
+
+    #[test]
+    fn required_space() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let required = archive.required_space().unwrap();
+        let total: u64 = archive
+            .files_by_size(false)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.size().unwrap() as u64)
+            .sum();
+        assert!(required >= total);
+        assert_eq!(required % 4096, 0);
+    }
+
+    #[cfg(feature = "fs2")]
+    #[test]
+    fn check_space() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            archive
+                .check_space(temp_dir.path().join("not/yet/created"))
+                .unwrap(),
+            archive.required_space().unwrap()
+        );
+    }
+
+    #[test]
+    fn sha256_of_file() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        for file in ["rules.txt", "content/Pack/Bootup.pack"] {
+            assert_eq!(
+                archive.sha256_of_file(file).unwrap(),
+                sha::sha256(&archive.read_file(file).unwrap())
+            );
+        }
+        assert!(archive.sha256_of_file("nothing.txt").is_err());
+    }
+
+    #[test]
+    fn range_checksums() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let file = "content/Pack/Bootup.pack";
+        let prefix = archive.read_from_file(file, 0, 100_000).unwrap();
+        let mut crc = crc::Crc32::new();
+        crc.update(&prefix);
+        assert_eq!(archive.range_crc32(file, 0..100_000).unwrap(), crc.finish());
+        assert_eq!(
+            archive.range_sha256(file, 0..100_000).unwrap(),
+            sha::sha256(&prefix)
+        );
+        assert_eq!(
+            archive.range_sha256("rules.txt", 0..300).unwrap(),
+            archive.sha256_of_file("rules.txt").unwrap()
+        );
+        assert!(matches!(
+            archive.range_crc32("rules.txt", 0..301),
+            Err(ZArchiveError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn walk_tree() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        fn print_dir<'a, 'b>(archive: &'a ZArchiveReader, dir: &'b DirEntry<'a>)
+        where
+            'a: 'b,
+        {
+            for entry in archive.iter_dir(dir).unwrap() {
+                if entry.is_file() {
+                    println!("{}", entry.full_path());
+                } else {
+                    print_dir(archive, &entry);
+                }
+            }
+        }
+
+        for entry in archive.iter().unwrap() {
+            if entry.is_file() {
+                println!("{}", entry.full_path());
+            } else {
+                print_dir(&archive, &entry);
+            }
+        }
+    }
+
+    #[test]
+    fn walk_depth() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut files = 0;
+        for entry in archive.walk_with_depth().unwrap() {
+            let (depth, entry) = entry.unwrap();
+            println!("{}{}", "  ".repeat(depth), entry.name());
+            assert_eq!(depth, entry.full_path().matches('/').count());
+            if entry.is_file() {
+                files += 1;
+            }
+        }
+        assert_eq!(files, archive.get_files().unwrap().len());
+    }
+
+    #[test]
+    fn walk_sorted() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let sorted: Vec<String> = archive
+            .walk_sorted()
+            .unwrap()
+            .map(|entry| entry.unwrap().full_path().to_owned())
+            .collect();
+        assert_eq!(sorted.len(), archive.walk_with_depth().unwrap().count());
+        let mut expected = sorted.clone();
+        expected.sort_by(|a, b| a.split('/').cmp(b.split('/')));
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn depth_limited_walk() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let top: Vec<_> = archive
+            .walk_max_depth(0)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(top.len(), 3);
+        assert!(top
+            .iter()
+            .all(|entry| entry.is_file() || entry.has_unexpanded_children()));
+        for entry in archive.walk_max_depth(2).unwrap() {
+            let entry = entry.unwrap();
+            assert!(entry.full_path().matches('/').count() <= 2);
+        }
+    }
+
+    #[test]
+    fn counted_walk() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let (count, walk) = archive.walk_counted().unwrap();
+        assert_eq!(walk.len(), count);
+        assert_eq!(walk.count(), count);
+    }
+
+    #[test]
+    fn walk_matching() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let models: Vec<_> = archive
+            .walk_matching("content/Model/*.sbfres")
+            .unwrap()
+            .map(|entry| entry.unwrap().full_path().to_owned())
+            .collect();
+        let expected: Vec<_> = archive
+            .get_files()
+            .unwrap()
+            .into_iter()
+            .filter(|file| {
+                file.strip_prefix("content/Model/")
+                    .is_some_and(|name| !name.contains('/') && name.ends_with(".sbfres"))
+            })
+            .collect();
+        assert!(!models.is_empty());
+        assert_eq!(models.len(), expected.len());
+        assert!(archive
+            .walk_matching("content/**")
+            .unwrap()
+            .all(|entry| entry.unwrap().full_path().starts_with("content/")));
+        assert_eq!(archive.walk_matching("**/RULES.txt").unwrap().count(), 1);
+        assert_eq!(archive.walk_matching("nothing/**").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn walk() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut files: Vec<String> = archive
+            .walk()
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|entry| entry.is_file())
+            .map(|entry| entry.full_path().to_owned())
+            .collect();
+        let mut expected = archive.get_files().unwrap();
+        files.sort();
+        expected.sort();
+        assert_eq!(files, expected);
+        let rules = archive
+            .walk()
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|entry| entry.full_path() == "rules.txt")
+            .unwrap();
+        assert_eq!(rules.size(), Some(300));
+        assert!(archive
+            .walk()
+            .unwrap()
+            .map(Result::unwrap)
+            .any(|entry| entry.is_dir() && entry.full_path() == "content/Model"));
+    }
+
+    #[test]
+    fn dir_changes() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        archive.extract(temp_dir.path()).unwrap();
+        assert_eq!(
+            archive.dir_changes(temp_dir.path()).unwrap(),
+            DirChanges::default()
+        );
+        let mut rules = archive.read_file("rules.txt").unwrap();
+        rules[0] ^= 0xFF;
+        std::fs::write(temp_dir.path().join("rules.txt"), &rules).unwrap();
+        std::fs::remove_file(temp_dir.path().join("content/Model/Item_Feather.sbfres")).unwrap();
+        std::fs::write(temp_dir.path().join("content/new.txt"), b"new").unwrap();
+        assert_eq!(
+            archive.dir_changes(temp_dir.path()).unwrap(),
+            DirChanges {
+                added: vec!["content/new.txt".to_owned()],
+                deleted: vec!["content/Model/Item_Feather.sbfres".to_owned()],
+                modified: vec!["rules.txt".to_owned()],
+            }
+        );
+        assert!(archive
+            .dir_changes(temp_dir.path().join("rules.txt"))
+            .is_err());
+    }
+
+    #[test]
+    fn for_each_file_data() {
+        use std::ops::ControlFlow;
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut sizes = Vec::new();
+        archive
+            .for_each_file_data(|path, data| {
+                sizes.push((path.to_owned(), data.len()));
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        let files = archive.get_files().unwrap();
+        assert_eq!(sizes.len(), files.len());
+        assert!(sizes.contains(&("rules.txt".to_owned(), 300)));
+        let mut seen = 0;
+        archive
+            .for_each_file_data(|_, _| {
+                seen += 1;
+                if seen == 3 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(seen, 3);
+    }
+
+    #[test]
+    fn extract_file() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        archive
+            .extract_file_to("content/Actor/ActorInfo.product.sbyml", temp_file.path())
+            .unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        archive
+            .extract_file_into("content/Actor/ActorInfo.product.sbyml", temp_dir.path())
+            .unwrap();
+        assert!(temp_dir
+            .path()
+            .join("content/Actor/ActorInfo.product.sbyml")
+            .is_file());
+    }
+
+    #[test]
+    fn verify_extracted() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        archive
+            .extract_file_to("content/Model/Item_Feather.sbfres", temp_file.path())
+            .unwrap();
+        assert!(archive
+            .verify_extracted("content/Model/Item_Feather.sbfres", temp_file.path())
+            .unwrap());
+        assert!(!archive
+            .verify_extracted("content/Model/Item_Ore_Iron.sbfres", temp_file.path())
+            .unwrap());
+    }
+
+    #[cfg(feature = "tempfile")]
+    #[test]
+    fn extract_to_temp() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp = archive.extract_to_temp("rules.txt").unwrap();
+        assert_eq!(temp.path().extension().unwrap(), "txt");
+        assert_eq!(
+            std::fs::read(temp.path()).unwrap(),
+            archive.read_file("rules.txt").unwrap()
+        );
+        let path = temp.path().to_owned();
+        drop(temp);
+        assert!(!path.exists());
+        assert!(matches!(
+            archive.extract_to_temp("content"),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+    }
+
+    #[cfg(feature = "memmap2")]
+    #[test]
+    fn extract_file_mmap() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        for file in [
+            "content/Pack/Bootup.pack",
+            "aoc/0010/Pack/AocMainField.pack",
+            "rules.txt",
+        ] {
+            let dest = temp_dir.path().join(file);
+            archive.extract_file_mmap(file, &dest).unwrap();
+            assert!(std::fs::read(&dest).unwrap() == archive.read_file(file).unwrap());
+        }
+        assert!(matches!(
+            archive.extract_file_mmap("content", temp_dir.path().join("content.bin")),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+    }
+
+    #[test]
+    fn deep_full_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input = temp_dir.path().join("input");
+        let deep = "a/b/c/d/e/f/g/h";
+        std::fs::create_dir_all(input.join(deep)).unwrap();
+        std::fs::write(input.join(deep).join("deep.txt"), b"deep").unwrap();
+        let path = temp_dir.path().join("deep.zar");
+        crate::pack(&input, &path).unwrap();
+        let archive = ZArchiveReader::open(&path).unwrap();
+
+        fn collect<'a, 'b>(
+            archive: &'a ZArchiveReader,
+            dir: &'b DirEntry<'a>,
+            paths: &mut Vec<String>,
+        ) where
+            'a: 'b,
+        {
+            for entry in archive.iter_dir(dir).unwrap() {
+                paths.push(entry.full_path());
+                if entry.is_dir() {
+                    collect(archive, &entry, paths);
+                }
+            }
+        }
+
+        let mut paths = vec![];
+        for entry in archive.iter().unwrap() {
+            paths.push(entry.full_path());
+            collect(&archive, &entry, &mut paths);
+        }
+        let expected: Vec<String> = (1..=8)
+            .map(|depth| deep[..depth * 2 - 1].to_owned())
+            .chain([format!("{deep}/deep.txt")])
+            .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(archive.read_file(&expected[8]).unwrap(), b"deep");
+    }
+
+    #[test]
+    fn extract_each() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("rules.txt")).unwrap();
+        let mut failed = vec![];
+        let mut bytes = 0;
+        archive
+            .extract_each(temp_dir.path(), |path, result| match result {
+                Ok(written) => bytes += written,
+                Err(_) => failed.push(path.to_owned()),
+            })
+            .unwrap();
+        assert_eq!(failed, vec!["rules.txt".to_owned()]);
+        assert!(bytes > 0);
+        assert!(temp_dir.path().join("content/Pack/Bootup.pack").is_file());
+    }
+
+    #[test]
+    fn extract_all() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let files = archive.get_files().unwrap();
+        archive.extract(temp_dir.path()).unwrap();
+        for file in files {
+            assert!(temp_dir.path().join(file).exists());
+        }
+    }
+
+    #[test]
+    fn extract_with_progress() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut reports = vec![];
+        archive
+            .extract_with_progress(temp_dir.path(), |progress| {
+                assert!(!temp_dir.path().join(progress.current_file).exists());
+                reports.push((
+                    progress.current_file.to_owned(),
+                    progress.files_done,
+                    progress.files_total,
+                    progress.bytes_done,
+                    progress.bytes_total,
+                ));
+            })
+            .unwrap();
+        let files = archive.get_files().unwrap();
+        let total: u64 = files
+            .iter()
+            .map(|file| archive.file_size(file).unwrap() as u64)
+            .sum();
+        assert_eq!(reports.len(), files.len());
+        assert_eq!(reports[0].1, 0);
+        assert_eq!(reports[0].3, 0);
+        assert!(reports
+            .iter()
+            .enumerate()
+            .all(|(i, report)| report.1 == i && report.2 == files.len() && report.4 == total));
+        let (last, _, _, bytes_done, _) = reports.last().unwrap();
+        assert_eq!(bytes_done + archive.file_size(last).unwrap() as u64, total);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("rules.txt")).unwrap(),
+            archive.read_file("rules.txt").unwrap()
+        );
+    }
 
+    #[cfg(feature = "rayon")]
     #[test]
-    fn file_list() {
+    fn extract_adaptive() {
         let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        archive.extract_adaptive(temp_dir.path()).unwrap();
         for file in archive.get_files().unwrap() {
-            println!("{}", file);
+            assert_eq!(
+                std::fs::read(temp_dir.path().join(&file)).unwrap(),
+                archive.read_file(&file).unwrap()
+            );
         }
+
+        let memory =
+            ZArchiveReader::from_bytes(std::fs::read("test/crafting.zar").unwrap()).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        memory.extract_adaptive(temp_dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("rules.txt")).unwrap(),
+            archive.read_file("rules.txt").unwrap()
+        );
     }
 
     #[test]
-    fn walk_tree() {
+    fn extract_filtered() {
+        let temp_dir = tempfile::tempdir().unwrap();
         let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
-        fn print_dir<'a, 'b>(archive: &'a ZArchiveReader, dir: &'b DirEntry<'a>)
-        where
-            'a: 'b,
-        {
-            for entry in archive.iter_dir(dir).unwrap() {
-                if entry.is_file() {
-                    println!("{}", entry.full_path());
-                } else {
-                    print_dir(archive, &entry);
-                }
-            }
-        }
+        archive
+            .extract_filtered(temp_dir.path(), |path, size| {
+                path.starts_with("content/Model") && size.unwrap() < 20000
+            })
+            .unwrap();
+        assert!(temp_dir
+            .path()
+            .join("content/Model/Item_Ore_Iron.sbfres")
+            .exists());
+        assert!(!temp_dir
+            .path()
+            .join("content/Model/Item_Feather.sbfres")
+            .exists());
+        assert!(!temp_dir.path().join("rules.txt").exists());
+    }
 
-        for entry in archive.iter().unwrap() {
-            if entry.is_file() {
-                println!("{}", entry.full_path());
-            } else {
-                print_dir(&archive, &entry);
-            }
+    #[test]
+    fn extract_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let manifest = archive.extract_with_manifest(temp_dir.path()).unwrap();
+        assert_eq!(manifest.entries.len(), archive.get_files().unwrap().len());
+        for entry in manifest.entries {
+            let data = std::fs::read(temp_dir.path().join(&entry.path)).unwrap();
+            assert_eq!(data.len() as u64, entry.size);
+            assert_eq!(crate::sha::sha256(&data), entry.sha256);
         }
     }
 
     #[test]
-    fn extract_file() {
-        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
-        let temp_file = tempfile::NamedTempFile::new().unwrap();
+    fn strip_common_prefix() {
+        let (temp_dir, path) = build_archive(&[
+            ("content/Actor/a.txt", b"a"),
+            ("content/Pack/b.txt", b"b"),
+            ("content/Empty/", b""),
+        ]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        let out = temp_dir.path().join("out");
         archive
-            .extract_file("content/Actor/ActorInfo.product.sbyml", temp_file.path())
+            .extract_with_options(
+                &out,
+                ExtractOptions {
+                    create_empty_dirs: true,
+                    strip_common_prefix: true,
+                },
+            )
             .unwrap();
+        assert_eq!(std::fs::read(out.join("Actor/a.txt")).unwrap(), b"a");
+        assert_eq!(std::fs::read(out.join("Pack/b.txt")).unwrap(), b"b");
+        assert!(out.join("Empty").is_dir());
+        assert!(!out.join("content").exists());
     }
 
     #[test]
-    fn extract_all() {
+    fn extract_changed() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
         let temp_dir = tempfile::tempdir().unwrap();
+        let files = archive.get_files().unwrap().len();
+        let report = archive.extract_changed(temp_dir.path(), true).unwrap();
+        assert_eq!(report.added.len(), files);
+        std::fs::write(temp_dir.path().join("rules.txt"), b"changed").unwrap();
+        let mut rules = archive.read_file("rules.txt").unwrap();
+        rules[0] ^= 0xFF;
+        let feather = temp_dir.path().join("content/Model/Item_Feather.sbfres");
+        std::fs::remove_file(&feather).unwrap();
+        let report = archive.extract_changed(temp_dir.path(), true).unwrap();
+        assert_eq!(report.updated, vec!["rules.txt".to_owned()]);
+        assert_eq!(
+            report.added,
+            vec!["content/Model/Item_Feather.sbfres".to_owned()]
+        );
+        assert_eq!(report.skipped.len(), files - 2);
+        std::fs::write(temp_dir.path().join("rules.txt"), &rules).unwrap();
+        let report = archive.extract_changed(temp_dir.path(), false).unwrap();
+        assert_eq!(report.skipped.len(), files);
+        let report = archive.extract_changed(temp_dir.path(), true).unwrap();
+        assert_eq!(report.updated, vec!["rules.txt".to_owned()]);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn repack_as_zip() {
         let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("crafting.zip");
+        archive
+            .repack_as_zip(
+                &dest,
+                ZipOptions {
+                    include_empty_dirs: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&dest).unwrap()).unwrap();
         let files = archive.get_files().unwrap();
-        archive.extract(temp_dir.path()).unwrap();
-        for file in files {
-            assert!(temp_dir.path().join(file).exists());
+        let empty_dirs = archive.empty_dirs().unwrap();
+        assert_eq!(zip.len(), files.len() + empty_dirs.len());
+        for file in ["rules.txt", "content/Model/Item_Feather.sbfres"] {
+            let mut data = vec![];
+            std::io::Read::read_to_end(&mut zip.by_name(file).unwrap(), &mut data).unwrap();
+            assert!(data == archive.read_file(file).unwrap());
         }
+        assert_eq!(
+            zip.by_name("aoc/0010/Pack/AocMainField.pack")
+                .unwrap()
+                .size(),
+            0
+        );
+    }
+
+    #[test]
+    fn copy_archive() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut copy = Vec::new();
+        let written = archive.copy_archive_to(&mut copy).unwrap();
+        assert_eq!(written as usize, copy.len());
+        assert_eq!(copy, std::fs::read("test/crafting.zar").unwrap());
     }
 
     #[test]
@@ -541,6 +4556,391 @@ mod tests {
         assert_eq!(&data[..4], b"SARC");
     }
 
+    #[test]
+    fn pooled_read() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        for _ in 0..3 {
+            let len = archive
+                .with_read_buffer("content/Model/Item_Feather.sbfres", |data| data.len())
+                .unwrap();
+            assert_eq!(len, 66416);
+        }
+        assert!(archive.with_read_buffer("not/a/file", |_| ()).is_err());
+    }
+
+    #[test]
+    fn copy_range() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let full = archive.read_file("content/Pack/Bootup.pack").unwrap();
+        let mut out = vec![];
+        let copied = archive
+            .copy_range_to("content/Pack/Bootup.pack", 100..200_000, &mut out)
+            .unwrap();
+        assert_eq!(copied, 199_900);
+        assert_eq!(&out[..], &full[100..200_000]);
+        assert!(archive
+            .copy_range_to("rules.txt", 0..301, &mut std::io::sink())
+            .is_err());
+    }
+
+    #[test]
+    fn read_clamped() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let rules = archive.read_file("rules.txt").unwrap();
+        assert_eq!(
+            archive
+                .read_from_file_clamped("rules.txt", 250, 100)
+                .unwrap(),
+            &rules[250..]
+        );
+        assert!(archive
+            .read_from_file_clamped("rules.txt", 1000, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            archive
+                .read_from_file_clamped("rules.txt", 0, u64::MAX)
+                .unwrap(),
+            rules
+        );
+        assert!(archive.read_from_file_clamped("nothing.txt", 0, 1).is_err());
+    }
+
+    #[test]
+    fn read_small() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let rules = archive.read_small("rules.txt").unwrap();
+        assert!(!rules.spilled());
+        assert_eq!(rules.as_slice(), archive.read_file("rules.txt").unwrap());
+        assert!(archive
+            .read_small("aoc/0010/Pack/AocMainField.pack")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            archive.read_small("content/Pack/Bootup.pack"),
+            Err(ZArchiveError::FileTooLarge { limit: 4096, .. })
+        ));
+        assert!(matches!(
+            archive.read_small("missing.txt"),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+    }
+
+    #[test]
+    fn try_read_file() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.try_read_file("rules.txt").unwrap().len(), 300);
+        assert!(matches!(
+            archive.try_read_file("missing.txt"),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+        assert!(matches!(
+            archive.try_read_file("content"),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+        assert!(archive.read_file("missing.txt").is_none());
+    }
+
+    #[test]
+    fn try_read_from_file() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(
+            archive
+                .try_read_from_file("content/Pack/Bootup.pack", 0, 4)
+                .unwrap(),
+            b"SARC"
+        );
+        assert_eq!(
+            archive.try_read_from_file("rules.txt", 100, 200).unwrap(),
+            archive.read_from_file("rules.txt", 100, 200).unwrap()
+        );
+        assert!(matches!(
+            archive.try_read_from_file("missing.txt", 0, 1),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+        assert!(matches!(
+            archive.try_read_from_file("rules.txt", 200, 101),
+            Err(ZArchiveError::OutOfBounds { size: 300, .. })
+        ));
+        assert!(matches!(
+            archive.try_read_from_file("rules.txt", u64::MAX, 2),
+            Err(ZArchiveError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn read_or_empty() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.read_file_or_empty("rules.txt").unwrap().len(), 300);
+        assert!(archive
+            .read_file_or_empty("nothing.txt")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn read_file_decoded() {
+        fn sarc_header(data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data[..8].to_vec())
+        }
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut decoders = DecoderRegistry::new();
+        decoders.register(*b"SARC", sarc_header);
+        let data = archive
+            .read_file_decoded("content/Pack/Bootup.pack", &decoders)
+            .unwrap();
+        assert_eq!(data.len(), 8);
+        assert_eq!(
+            archive.read_file_decoded("rules.txt", &decoders).unwrap(),
+            archive.read_file("rules.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn read_transformed() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let file = "content/Model/Item_Feather.sbfres";
+        let chunks: Vec<Vec<u8>> = archive
+            .read_transformed(file, |chunk| chunk.iter_mut().for_each(|b| *b ^= 0x5a))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= BLOCK_SIZE));
+        let restored: Vec<u8> = chunks.concat().into_iter().map(|b| b ^ 0x5a).collect();
+        assert_eq!(restored, archive.read_file(file).unwrap());
+        assert!(archive.read_transformed("missing.bin", |_| ()).is_err());
+    }
+
+    #[test]
+    fn read_file_into() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut buf = vec![0xAA; 100_000];
+        let read = archive
+            .read_file_into("content/Model/Item_Feather.sbfres", &mut buf)
+            .unwrap();
+        assert_eq!(read, 66416);
+        assert!(
+            buf == archive
+                .read_file("content/Model/Item_Feather.sbfres")
+                .unwrap()
+        );
+        let capacity = buf.capacity();
+        assert_eq!(archive.read_file_into("rules.txt", &mut buf).unwrap(), 300);
+        assert!(buf == archive.read_file("rules.txt").unwrap());
+        assert_eq!(buf.capacity(), capacity);
+        assert!(archive.read_file_into("missing.txt", &mut buf).is_err());
+    }
+
+    #[test]
+    fn read_into_arena() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let mut arena = Vec::new();
+        let rules = archive.read_into_arena("rules.txt", &mut arena).unwrap();
+        let feather = archive
+            .read_into_arena("content/Model/Item_Feather.sbfres", &mut arena)
+            .unwrap();
+        assert_eq!(rules, 0..300);
+        assert_eq!(feather, 300..300 + 66416);
+        assert_eq!(&arena[rules], &archive.read_file("rules.txt").unwrap()[..]);
+        assert_eq!(
+            &arena[feather],
+            &archive
+                .read_file("content/Model/Item_Feather.sbfres")
+                .unwrap()[..]
+        );
+        assert!(archive.read_into_arena("nothing.txt", &mut arena).is_err());
+        assert_eq!(arena.len(), 300 + 66416);
+    }
+
+    #[test]
+    fn typed_reads() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let pack = "content/Pack/Bootup.pack";
+        let data = archive.read_from_file(pack, 0, 16).unwrap();
+        assert_eq!(
+            archive.read_u32_at(pack, 0, Endian::Big).unwrap(),
+            u32::from_be_bytes(*b"SARC")
+        );
+        assert_eq!(
+            archive.read_u16_at(pack, 4, Endian::Little).unwrap(),
+            u16::from_le_bytes([data[4], data[5]])
+        );
+        assert_eq!(
+            archive.read_i32_at(pack, 8, Endian::Little).unwrap(),
+            i32::from_le_bytes(data[8..12].try_into().unwrap())
+        );
+        assert_eq!(
+            archive.read_u64_at(pack, 8, Endian::Big).unwrap(),
+            u64::from_be_bytes(data[8..16].try_into().unwrap())
+        );
+        assert!(matches!(
+            archive.read_u32_at("rules.txt", 298, Endian::Big),
+            Err(ZArchiveError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            archive.read_u32_at("rules.txt", u64::MAX - 1, Endian::Big),
+            Err(ZArchiveError::OutOfBounds { size: 300, .. })
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn read_file_parallel() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        for file in archive.get_files().unwrap() {
+            assert_eq!(
+                archive.read_file_parallel(&file).unwrap(),
+                archive.try_read_file(&file).unwrap()
+            );
+        }
+        assert!(matches!(
+            archive.read_file_parallel("content"),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+
+        // A file starting mid-block with both stored and compressed blocks,
+        // and one long enough to be read in more than one batch.
+        let mut big = noise(BLOCK_SIZE * 3 / 2);
+        big.resize(BLOCK_SIZE * 3 + 17, b'z');
+        let long: Vec<u8> = (0..BLOCK_SIZE * (PARALLEL_READ_BATCH + 4))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let (_temp_dir, path) = build_archive(&[
+            ("head.txt", b"head"),
+            ("big.bin", &big),
+            ("long.bin", &long),
+        ]);
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert!(archive.read_file_parallel("big.bin").unwrap() == big);
+        assert!(archive.read_file_parallel("long.bin").unwrap() == long);
+        assert_eq!(archive.read_file_parallel("head.txt").unwrap(), b"head");
+    }
+
+    #[test]
+    fn corrupted_block() {
+        let (_temp_dir, path) = build_archive(&[("data.txt", &[b'a'; 2 * BLOCK_SIZE])]);
+        let ranges = ZArchiveReader::open(&path)
+            .unwrap()
+            .physical_range("data.txt", 0..1)
+            .unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        let start = ranges[0].start as usize;
+        bytes[start..start + 4].copy_from_slice(b"junk");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert!(matches!(
+            archive.try_read_file("data.txt"),
+            Err(ZArchiveError::DecompressionFailed { path, .. }) if path == "data.txt"
+        ));
+        assert!(matches!(
+            archive.try_read_from_file("data.txt", 0, 16),
+            Err(ZArchiveError::DecompressionFailed { .. })
+        ));
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(ZArchiveReader::open(&path).is_err());
+    }
+
+    #[test]
+    fn open_file() {
+        use std::io::{Read, Seek, SeekFrom};
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let data = archive
+            .read_file("content/Model/Item_Feather.sbfres")
+            .unwrap();
+        let mut file = archive
+            .open_file("content/Model/Item_Feather.sbfres")
+            .unwrap();
+        assert_eq!(file.size(), data.len() as u64);
+        let mut all = vec![];
+        file.read_to_end(&mut all).unwrap();
+        assert!(all == data);
+
+        // Across the block boundary at 64 KiB.
+        let mut buf = [0; 16];
+        file.seek(SeekFrom::Start(65530)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[65530..65546]);
+        assert_eq!(file.seek(SeekFrom::Current(-32)).unwrap(), 65514);
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[65514..65530]);
+        assert_eq!(file.seek(SeekFrom::End(-4)).unwrap(), data.len() as u64 - 4);
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], data[data.len() - 4..]);
+        file.seek(SeekFrom::End(10)).unwrap();
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        assert!(file.seek(SeekFrom::Current(-100_000_000)).is_err());
+
+        assert!(matches!(
+            archive.open_file("missing.txt"),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+    }
+
+    #[test]
+    fn open_file_with_read_ahead() {
+        use std::io::{Read, Seek, SeekFrom};
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let data = archive
+            .read_file("content/Model/Item_Feather.sbfres")
+            .unwrap();
+        for read_ahead in [0, 1000, BLOCK_SIZE] {
+            let mut file = archive
+                .open_file_with_read_ahead("content/Model/Item_Feather.sbfres", read_ahead)
+                .unwrap();
+            assert_eq!(file.read_ahead(), read_ahead);
+            let mut all = vec![];
+            let mut buf = [0; 7];
+            loop {
+                let read = file.read(&mut buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                all.extend_from_slice(&buf[..read]);
+            }
+            assert!(all == data);
+
+            file.seek(SeekFrom::Start(65530)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[65530..65537]);
+            file.seek(SeekFrom::Current(-14)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[65523..65530]);
+            file.seek(SeekFrom::End(-3)).unwrap();
+            assert_eq!(file.read(&mut buf).unwrap(), 3);
+            assert_eq!(buf[..3], data[data.len() - 3..]);
+        }
+    }
+
+    #[test]
+    fn concat_reader() {
+        use std::io::Read;
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let paths = [
+            "rules.txt",
+            "aoc/0010/Pack/AocMainField.pack",
+            "content/Pack/Bootup.pack",
+            "rules.txt",
+        ];
+        let mut expected = Vec::new();
+        for path in paths {
+            expected.extend(archive.read_file(path).unwrap());
+        }
+        let mut data = Vec::new();
+        archive
+            .concat_reader(&paths)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data.len(), 300 + 29021400 + 300);
+        assert!(data == expected);
+        assert!(matches!(
+            archive.concat_reader(&["rules.txt", "missing.txt"]),
+            Err(ZArchiveError::MissingFile(_))
+        ));
+    }
+
     #[test]
     fn concurrency() {
         use rayon::prelude::*;
@@ -556,6 +4956,311 @@ mod tests {
         });
     }
 
+    #[test]
+    fn open_unsync() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let local = ZArchiveReader::open_unsync("test/crafting.zar").unwrap();
+        assert_eq!(local.file_size("rules.txt"), Some(300));
+        assert_eq!(local.read_file("rules.txt"), archive.read_file("rules.txt"));
+        assert_eq!(
+            local.read_from_file("rules.txt", 10, 20),
+            archive.read_from_file("rules.txt", 10, 20)
+        );
+        assert!(local.read_file("nothing.txt").is_none());
+        assert!(local.read_from_file("rules.txt", 290, 20).is_none());
+        assert!(matches!(
+            local.try_read_from_file("rules.txt", u64::MAX, 2),
+            Err(ZArchiveError::OutOfBounds { size: 300, .. })
+        ));
+        assert!(matches!(
+            local.try_read_file("content"),
+            Err(ZArchiveError::NotAFile(_))
+        ));
+        assert_eq!(local.get_files().unwrap(), archive.get_files().unwrap());
+        let temp_dir = tempfile::tempdir().unwrap();
+        local.extract_file("rules.txt", temp_dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("rules.txt")).unwrap(),
+            archive.read_file("rules.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("live.zar");
+        let write = |name: &str, data: &[u8]| {
+            let mut writer = crate::ZArchiveWriter::new(&path).unwrap();
+            writer.start_file(name).unwrap();
+            writer.append_data(data).unwrap();
+            writer.finalize().unwrap();
+        };
+        write("a.txt", b"old");
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert_eq!(archive.read_file("a.txt").unwrap(), b"old");
+        write("b.txt", b"new");
+        archive.reopen().unwrap();
+        assert!(archive.read_file("a.txt").is_none());
+        assert_eq!(archive.read_file("b.txt").unwrap(), b"new");
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(archive.reopen().is_err());
+        assert_eq!(archive.read_file("b.txt").unwrap(), b"new");
+    }
+
+    #[test]
+    fn reopen_per_read() {
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let reopening = ZArchiveReader::builder()
+            .keep_open(false)
+            .open("test/crafting.zar")
+            .unwrap();
+        for file in ["rules.txt", "content/Pack/Bootup.pack", "rules.txt"] {
+            assert_eq!(reopening.read_file(file), archive.read_file(file));
+        }
+        let mut copy = Vec::new();
+        reopening.copy_archive_to(&mut copy).unwrap();
+        assert_eq!(copy, std::fs::read("test/crafting.zar").unwrap());
+    }
+
+    #[test]
+    fn retry() {
+        let archive = ZArchiveReader::builder()
+            .keep_open(false)
+            .retry(3, std::time::Duration::from_millis(1))
+            .open("test/crafting.zar")
+            .unwrap();
+        assert_eq!(
+            archive.read_file("content/Pack/Bootup.pack").unwrap(),
+            ZArchiveReader::open("test/crafting.zar")
+                .unwrap()
+                .read_file("content/Pack/Bootup.pack")
+                .unwrap()
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("gone.zar");
+        std::fs::copy("test/crafting.zar", &path).unwrap();
+        let archive = ZArchiveReader::builder()
+            .keep_open(false)
+            .retry(2, std::time::Duration::from_millis(1))
+            .open(&path)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let start = std::time::Instant::now();
+        assert!(archive.try_read_from_file("rules.txt", 0, 300).is_err());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(3));
+    }
+
+    #[test]
+    fn strict_open() {
+        let archive = ZArchiveReader::builder()
+            .strict(true)
+            .open("test/crafting.zar")
+            .unwrap();
+        assert_eq!(archive.iter().unwrap().count(), 3);
+
+        let (_temp_dir, path) = build_archive(&[("dir/aa.bin", b"x"), ("dir/ab.bin", b"x")]);
+        // rename dir/ab.bin in the name table so the directory holds the same
+        // name twice, differing only in case
+        let mut bytes = std::fs::read(&path).unwrap();
+        let at = bytes
+            .windows(6)
+            .position(|window| window == b"ab.bin")
+            .unwrap();
+        bytes[at..at + 6].copy_from_slice(b"AA.bin");
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert!(ZArchiveReader::open(&path).is_ok());
+        match ZArchiveReader::builder().strict(true).open(&path) {
+            Err(ZArchiveError::DuplicateEntry { parent, name }) => {
+                assert_eq!(parent, "dir");
+                assert!(name.eq_ignore_ascii_case("aa.bin"));
+            }
+            other => panic!("expected DuplicateEntry, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn on_event() {
+        use std::sync::{Arc, Mutex};
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let archive = ZArchiveReader::builder()
+            .on_event(move |event| {
+                let summary = match event {
+                    ReaderEvent::Open { .. } => "open".to_owned(),
+                    ReaderEvent::Lookup { path, found, .. } => format!("lookup {path} {found}"),
+                    ReaderEvent::Read { path, bytes, .. } => format!("read {path} {bytes}"),
+                    ReaderEvent::Walk { entries, .. } => format!("walk {entries}"),
+                };
+                sink.lock().unwrap().push(summary);
+            })
+            .open("test/crafting.zar")
+            .unwrap();
+        archive.read_file("rules.txt").unwrap();
+        archive.file_size("missing.txt");
+        let entries = archive.walk_with_depth().unwrap().count();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "open".to_owned(),
+                "read rules.txt 300".to_owned(),
+                "lookup missing.txt false".to_owned(),
+                format!("walk {entries}"),
+            ]
+        );
+
+        let quiet = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert!(quiet.1.is_none());
+        assert_eq!(quiet.walk_with_depth().unwrap().started, None);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let archive =
+            ZArchiveReader::from_bytes(std::fs::read("test/crafting.zar").unwrap()).unwrap();
+        let original = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.get_files().unwrap(), original.get_files().unwrap());
+        assert_eq!(
+            archive
+                .iter()
+                .unwrap()
+                .map(|e| e.full_path())
+                .collect::<Vec<_>>(),
+            original
+                .iter()
+                .unwrap()
+                .map(|e| e.full_path())
+                .collect::<Vec<_>>()
+        );
+        for file in ["rules.txt", "content/Model/Item_Feather.sbfres"] {
+            assert!(archive.read_file(file).unwrap() == original.read_file(file).unwrap());
+        }
+        assert!(archive.reopen().is_err());
+        assert!(archive.read_file("rules.txt").is_some());
+        assert!(ZArchiveReader::from_bytes(b"not an archive".to_vec()).is_err());
+    }
+
+    #[test]
+    fn from_reader() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        // Stands in for a remote source, counting the requests made to it.
+        struct Counting {
+            inner: std::io::Cursor<Vec<u8>>,
+            reads: Arc<AtomicUsize>,
+            seeks: Arc<AtomicUsize>,
+        }
+
+        impl std::io::Read for Counting {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.reads.fetch_add(1, Ordering::SeqCst);
+                self.inner.read(buf)
+            }
+        }
+
+        impl std::io::Seek for Counting {
+            fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                self.seeks.fetch_add(1, Ordering::SeqCst);
+                self.inner.seek(pos)
+            }
+        }
+
+        let reads = Arc::new(AtomicUsize::new(0));
+        let seeks = Arc::new(AtomicUsize::new(0));
+        let archive = ZArchiveReader::from_reader(Counting {
+            inner: std::io::Cursor::new(std::fs::read("test/crafting.zar").unwrap()),
+            reads: reads.clone(),
+            seeks: seeks.clone(),
+        })
+        .unwrap();
+        let original = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(archive.get_files().unwrap(), original.get_files().unwrap());
+        let counts = || {
+            (
+                reads.swap(0, Ordering::SeqCst),
+                seeks.swap(0, Ordering::SeqCst),
+            )
+        };
+        counts();
+        // Both blocks of the file come from a single seek and read.
+        let file = "content/Model/Item_Feather.sbfres";
+        assert!(archive.read_file(file).unwrap() == original.read_file(file).unwrap());
+        assert_eq!(counts(), (1, 1));
+        // The file after it lies within the same fetch.
+        let next = "content/Model/Item_Ore_Coal.sbfres";
+        assert!(archive.read_file(next).unwrap() == original.read_file(next).unwrap());
+        assert_eq!(counts(), (0, 0));
+        assert_eq!(
+            archive.read_file("rules.txt"),
+            original.read_file("rules.txt")
+        );
+        assert!(archive.reopen().is_err());
+
+        assert!(
+            ZArchiveReader::from_reader(std::io::Cursor::new(b"not an archive".to_vec())).is_err()
+        );
+    }
+
+    #[test]
+    fn open_at() {
+        let data = std::fs::read("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("container.bin");
+        let mut container = b"HEADER".repeat(1000);
+        container.extend_from_slice(&data);
+        container.extend_from_slice(b"TRAILING METADATA");
+        std::fs::write(&path, &container).unwrap();
+
+        let archive = ZArchiveReader::open_at(&path, 6000, data.len() as u64).unwrap();
+        let original = ZArchiveReader::open("test/crafting.zar").unwrap();
+        assert_eq!(
+            archive.read_file("rules.txt"),
+            original.read_file("rules.txt")
+        );
+        assert_eq!(
+            archive
+                .read_from_file("content/Pack/Bootup.pack", 0, 4)
+                .unwrap(),
+            b"SARC"
+        );
+        let mut copy = vec![];
+        assert_eq!(
+            archive.copy_archive_to(&mut copy).unwrap(),
+            data.len() as u64
+        );
+        assert!(copy == data);
+        archive.reopen().unwrap();
+        assert_eq!(archive.get_files().unwrap(), original.get_files().unwrap());
+
+        assert!(ZArchiveReader::open_at(&path, 0, data.len() as u64).is_err());
+        assert!(ZArchiveReader::open_at(&path, 6000, data.len() as u64 - 1).is_err());
+        assert!(ZArchiveReader::open_at(&path, u64::MAX, 100).is_err());
+    }
+
+    #[test]
+    fn open_non_ascii_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("Ärchive ñ 日本");
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("crafting é.zar");
+        std::fs::copy("test/crafting.zar", &path).unwrap();
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert_eq!(archive.file_size("rules.txt"), Some(300));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn open_extended_path() {
+        let path = std::fs::canonicalize("test/crafting.zar").unwrap();
+        assert!(path.to_str().unwrap().starts_with(r"\\?\"));
+        let archive = ZArchiveReader::open(&path).unwrap();
+        assert_eq!(archive.file_size("rules.txt"), Some(300));
+    }
+
     #[test]
     fn ffi_methods() {
         let mut archive: cxx::UniquePtr<ffi::ZArchiveReader> =
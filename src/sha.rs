@@ -0,0 +1,87 @@
+//! Thin wrapper over the SHA-256 implementation bundled with ZArchive.
+use std::{os::raw::c_void, ptr};
+
+#[repr(C)]
+struct Sha256Ctx {
+    hash: *mut u8,
+    chunk: [u8; 64],
+    chunk_pos: *mut u8,
+    space_left: usize,
+    total_len: usize,
+    h: [u32; 8],
+}
+
+extern "C" {
+    fn sha_256_init(sha_256: *mut Sha256Ctx, hash: *mut u8);
+    fn sha_256_write(sha_256: *mut Sha256Ctx, data: *const c_void, len: usize);
+    fn sha_256_close(sha_256: *mut Sha256Ctx) -> *mut u8;
+}
+
+/// Incremental SHA-256 hasher.
+pub(crate) struct Sha256 {
+    // Both are boxed because the C context keeps pointers into itself and into
+    // the hash buffer, so neither may move after initialization.
+    ctx: Box<Sha256Ctx>,
+    hash: Box<[u8; 32]>,
+}
+
+impl Sha256 {
+    pub(crate) fn new() -> Self {
+        let mut ctx = Box::new(Sha256Ctx {
+            hash: ptr::null_mut(),
+            chunk: [0; 64],
+            chunk_pos: ptr::null_mut(),
+            space_left: 0,
+            total_len: 0,
+            h: [0; 8],
+        });
+        let mut hash = Box::new([0; 32]);
+        unsafe { sha_256_init(&mut *ctx, hash.as_mut_ptr()) };
+        Sha256 { ctx, hash }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        unsafe { sha_256_write(&mut *self.ctx, data.as_ptr() as *const c_void, data.len()) };
+    }
+
+    pub(crate) fn finish(mut self) -> [u8; 32] {
+        unsafe { sha_256_close(&mut *self.ctx) };
+        *self.hash
+    }
+}
+
+impl std::io::Write for Sha256 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash a complete buffer in one call.
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn known_digest() {
+        let mut hasher = super::Sha256::new();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+        assert_eq!(
+            hasher.finish(),
+            [
+                0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+                0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+                0xf2, 0x00, 0x15, 0xad
+            ]
+        );
+    }
+}
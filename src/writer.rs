@@ -1,5 +1,466 @@
-use crate::{Result, ZArchiveError};
-use std::path::Path;
+use crate::{reader::ZArchiveReader, Result, ZArchiveError};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// How to resolve a path present in more than one source when merging archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// Keep the file from the first source containing it.
+    FirstWins,
+    /// Keep the file from the last source containing it.
+    LastWins,
+    /// Fail with [`ZArchiveError::Conflict`].
+    Error,
+}
+
+/// Totals for a completed packing operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PackSummary {
+    /// The number of files written to the archive.
+    pub files: usize,
+    /// The total uncompressed size of the files written.
+    pub bytes: u64,
+}
+
+/// Progress of [`pack_with_progress`], reported after each file is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackProgress<'a> {
+    /// The number of files written so far, including the current one.
+    pub files_done: usize,
+    /// The number of files being packed in total.
+    pub files_total: usize,
+    /// The number of bytes written so far, including the current file.
+    pub bytes_done: u64,
+    /// The number of bytes being packed in total.
+    pub bytes_total: u64,
+    /// The path in the archive of the file just written.
+    pub current_file: &'a str,
+}
+
+/// Decides whether a file should be compressed, given its path in the archive
+/// and the first chunk of data appended to it.
+pub type CompressPredicate = Box<dyn Fn(&str, &[u8]) -> bool + Send + Sync>;
+
+/// Settings for writing a new archive.
+pub struct PackOptions {
+    /// The zstd compression level for data blocks. Higher levels produce
+    /// smaller archives but compress more slowly. Defaults to 6.
+    pub compression_level: i32,
+    /// Decides for each file whether its data is compressed or stored raw,
+    /// e.g. to skip already-compressed textures and audio. Every file is
+    /// compressed if this is `None`, the default.
+    pub compress_predicate: Option<CompressPredicate>,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            compression_level: 6,
+            compress_predicate: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for PackOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PackOptions")
+            .field("compression_level", &self.compression_level)
+            .field("compress_predicate", &self.compress_predicate.is_some())
+            .finish()
+    }
+}
+
+/// Incrementally builds a new archive, one file at a time.
+///
+/// Data passed to [`append_data`](Self::append_data) is added to the most
+/// recently started file. The archive index is only written by
+/// [`finalize`](Self::finalize), so a writer dropped before finalizing leaves
+/// an incomplete archive behind (and logs a warning).
+pub struct ZArchiveWriter {
+    inner: cxx::UniquePtr<ffi::StreamingWriter>,
+    finalized: bool,
+    compress_predicate: Option<CompressPredicate>,
+    pending: Option<String>,
+}
+
+impl std::fmt::Debug for ZArchiveWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ZArchiveWriter")
+    }
+}
+
+unsafe impl Send for ZArchiveWriter {}
+
+impl ZArchiveWriter {
+    /// Create a new archive at the given path, replacing any existing file.
+    pub fn new(output: impl AsRef<Path>) -> Result<Self> {
+        let output = output.as_ref();
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            inner: ffi::NewStreamingWriter(output.to_str().ok_or_else(|| {
+                ZArchiveError::InvalidFilePath(output.to_string_lossy().to_string())
+            })?)?,
+            finalized: false,
+            compress_predicate: None,
+            pending: None,
+        })
+    }
+
+    /// Create a new archive at the given path with the given settings,
+    /// replacing any existing file. A compress predicate is called with the
+    /// first chunk appended to each file and decides how that file is stored.
+    pub fn with_options(output: impl AsRef<Path>, options: PackOptions) -> Result<Self> {
+        let mut writer = Self::new(output)?;
+        writer
+            .inner
+            .pin_mut()
+            .SetCompressionLevel(options.compression_level)?;
+        writer.compress_predicate = options.compress_predicate;
+        Ok(writer)
+    }
+
+    /// Set whether data appended from now on is compressed. Data is written in
+    /// 64 KiB blocks shared between neighbouring files, so the block holding
+    /// the end of one file and the start of the next is stored according to
+    /// the setting in effect when it fills up.
+    pub fn set_compression(&mut self, enabled: bool) -> Result<()> {
+        self.inner.pin_mut().SetCompressionEnabled(enabled)?;
+        Ok(())
+    }
+
+    /// Merge the files from several archives into one new archive, resolving
+    /// paths present in more than one source with `on_conflict`. Paths are
+    /// compared case-insensitively, as in archive lookups. File contents are
+    /// streamed block by block from the sources.
+    pub fn merge(
+        output: impl AsRef<Path>,
+        sources: &[ZArchiveReader],
+        on_conflict: ConflictPolicy,
+    ) -> Result<PackSummary> {
+        let mut chosen: Vec<(usize, String, u64)> = vec![];
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (source_idx, source) in sources.iter().enumerate() {
+            for entry in source.walk_with_depth()? {
+                let (_, entry) = entry?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let key = entry.full_path().to_ascii_lowercase();
+                let file = (
+                    source_idx,
+                    entry.full_path().to_owned(),
+                    entry.size().unwrap_or_default() as u64,
+                );
+                match seen.get(&key) {
+                    None => {
+                        seen.insert(key, chosen.len());
+                        chosen.push(file);
+                    }
+                    Some(&idx) => match on_conflict {
+                        ConflictPolicy::FirstWins => (),
+                        ConflictPolicy::LastWins => chosen[idx] = file,
+                        ConflictPolicy::Error => {
+                            return Err(ZArchiveError::Conflict(entry.full_path().to_owned()))
+                        }
+                    },
+                }
+            }
+        }
+        let mut writer = ZArchiveWriter::new(output)?;
+        let mut summary = PackSummary::default();
+        for (source_idx, path, size) in chosen {
+            writer.start_file(&path)?;
+            summary.bytes += sources[source_idx].copy_range_to(&path, 0..size, &mut writer)?;
+            summary.files += 1;
+        }
+        writer.finalize()?;
+        Ok(summary)
+    }
+
+    /// Copy every file and empty directory of `src` into a new archive, except
+    /// that files named in `overrides` are read from the given path on disk
+    /// instead. Overrides not present in `src` are added. Paths are compared
+    /// case-insensitively, as in archive lookups. Everything is streamed, so
+    /// nothing is extracted.
+    pub fn from_reader_with_overrides(
+        output: impl AsRef<Path>,
+        src: &ZArchiveReader,
+        overrides: &HashMap<String, PathBuf>,
+    ) -> Result<PackSummary> {
+        let mut remaining: HashMap<String, (&str, &PathBuf)> = overrides
+            .iter()
+            .map(|(path, source)| {
+                (
+                    path.trim_matches(&['/', '\\'][..]).to_ascii_lowercase(),
+                    (path.as_str(), source),
+                )
+            })
+            .collect();
+        let mut writer = ZArchiveWriter::new(output)?;
+        let mut summary = PackSummary::default();
+        for dir in src.empty_dirs()? {
+            writer.make_dir(&dir, true)?;
+        }
+        for entry in src.walk_with_depth()? {
+            let (_, entry) = entry?;
+            if !entry.is_file() {
+                continue;
+            }
+            writer.start_file(entry.full_path())?;
+            summary.bytes += match remaining.remove(&entry.full_path().to_ascii_lowercase()) {
+                Some((_, source)) => std::io::copy(&mut std::fs::File::open(source)?, &mut writer)?,
+                None => src.copy_range_to(
+                    entry.full_path(),
+                    0..entry.size().unwrap_or_default() as u64,
+                    &mut writer,
+                )?,
+            };
+            summary.files += 1;
+        }
+        let mut added: Vec<_> = remaining.into_values().collect();
+        added.sort();
+        for (path, source) in added {
+            writer.start_file(path.trim_matches(&['/', '\\'][..]))?;
+            summary.bytes += std::io::copy(&mut std::fs::File::open(source)?, &mut writer)?;
+            summary.files += 1;
+        }
+        writer.finalize()?;
+        Ok(summary)
+    }
+
+    /// Start a new file in the archive, which becomes the target of all
+    /// appended data until the next file is started. Parent directories are
+    /// created as needed.
+    pub fn start_file(&mut self, path: &str) -> Result<()> {
+        if let Some((parent, _)) = path.rsplit_once(&['/', '\\'][..]) {
+            self.make_dir(parent, true)?;
+        }
+        self.inner.pin_mut().StartNewFile(path)?;
+        if self.compress_predicate.is_some() {
+            self.pending = Some(path.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Append data to the current file.
+    pub fn append_data(&mut self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if let (Some(path), Some(predicate)) = (self.pending.take(), &self.compress_predicate) {
+            let compress = predicate(&path, data);
+            self.set_compression(compress)?;
+        }
+        self.inner.pin_mut().AppendData(data)?;
+        Ok(())
+    }
+
+    /// Create a directory in the archive. Only needed for empty directories,
+    /// since [`start_file`](Self::start_file) creates parent directories.
+    pub fn make_dir(&mut self, path: &str, recursive: bool) -> Result<()> {
+        self.inner.pin_mut().MakeDir(path, recursive)?;
+        Ok(())
+    }
+
+    /// Complete the archive being written, if it hasn't been finalized, and
+    /// start a new one at `output`. The writer's buffers and compression
+    /// settings carry over, so producing many archives in a row doesn't
+    /// reallocate between them. The file tree, current file and integrity
+    /// hash all start from scratch.
+    pub fn reset(&mut self, output: impl AsRef<Path>) -> Result<()> {
+        if !self.finalized {
+            self.inner.pin_mut().Finalize()?;
+            self.finalized = true;
+        }
+        let output = output.as_ref();
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        self.inner.pin_mut().Reset(output.to_str().ok_or_else(|| {
+            ZArchiveError::InvalidFilePath(output.to_string_lossy().to_string())
+        })?)?;
+        self.finalized = false;
+        self.pending = None;
+        Ok(())
+    }
+
+    /// Write the archive index and footer, completing the archive.
+    pub fn finalize(mut self) -> Result<()> {
+        self.inner.pin_mut().Finalize()?;
+        self.finalized = true;
+        Ok(())
+    }
+}
+
+impl Drop for ZArchiveWriter {
+    fn drop(&mut self) {
+        if !self.finalized {
+            log::warn!("ZArchiveWriter dropped without being finalized, the archive is incomplete");
+        }
+    }
+}
+
+impl Write for ZArchiveWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.append_data(buf).map_err(std::io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pack an archive from a sequence of archive paths and readers supplying
+/// their contents, written with the given settings. Each reader is streamed
+/// into the archive in turn, so contents can come from anywhere (memory, disk,
+/// network) without being staged first.
+pub fn pack_stream(
+    entries: impl IntoIterator<Item = Result<(String, Box<dyn Read>)>>,
+    output: impl AsRef<Path>,
+    options: PackOptions,
+) -> Result<()> {
+    let mut writer = ZArchiveWriter::with_options(output, options)?;
+    for entry in entries {
+        let (path, mut reader) = entry?;
+        writer.start_file(&path)?;
+        std::io::copy(&mut reader, &mut writer)?;
+    }
+    writer.finalize()
+}
+
+/// Pack an archive from a list of archive paths, calling `content` for each
+/// path in turn to produce the file's contents, written with the given
+/// settings. Only one file's contents are held at a time, and nothing is
+/// staged on disk.
+pub fn pack_generated(
+    tree: &[&str],
+    content: impl Fn(&str) -> Result<Vec<u8>>,
+    output: impl AsRef<Path>,
+    options: PackOptions,
+) -> Result<PackSummary> {
+    let mut writer = ZArchiveWriter::with_options(output, options)?;
+    let mut summary = PackSummary::default();
+    for &path in tree {
+        let data = content(path)?;
+        writer.start_file(path)?;
+        writer.append_data(&data)?;
+        summary.files += 1;
+        summary.bytes += data.len() as u64;
+    }
+    writer.finalize()?;
+    Ok(summary)
+}
+
+/// Copy every file and empty directory of an archive into a new archive
+/// written with the given settings, e.g. to raise the compression level. File
+/// contents are streamed block by block, so nothing is extracted to disk.
+pub fn recompress(
+    input: &ZArchiveReader,
+    output: impl AsRef<Path>,
+    options: PackOptions,
+) -> Result<PackSummary> {
+    let mut writer = ZArchiveWriter::with_options(output, options)?;
+    let mut summary = PackSummary::default();
+    for dir in input.empty_dirs()? {
+        writer.make_dir(&dir, true)?;
+    }
+    for entry in input.walk_with_depth()? {
+        let (_, entry) = entry?;
+        if !entry.is_file() {
+            continue;
+        }
+        writer.start_file(entry.full_path())?;
+        summary.bytes += input.copy_range_to(
+            entry.full_path(),
+            0..entry.size().unwrap_or_default() as u64,
+            &mut writer,
+        )?;
+        summary.files += 1;
+    }
+    writer.finalize()?;
+    Ok(summary)
+}
+
+/// Pack a directory into an archive like [`pack`], but with the given
+/// settings and calling `callback` after each file is written. The directory
+/// is scanned up front so the totals are known from the first report.
+pub fn pack_with_progress(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: PackOptions,
+    mut callback: impl FnMut(PackProgress<'_>),
+) -> Result<PackSummary> {
+    let input = input.as_ref();
+    if !input.is_dir() {
+        return Err(ZArchiveError::IOError(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Input file not found or not a directory",
+        )));
+    }
+    let mut files: Vec<(String, std::path::PathBuf, u64)> = vec![];
+    let mut dirs: Vec<String> = vec![];
+    let mut stack = vec![input.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let mut empty = true;
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            empty = false;
+            let path = entry.path();
+            // Follows symlinks, like `pack` does.
+            let metadata = std::fs::metadata(&path)?;
+            if metadata.is_dir() {
+                stack.push(path);
+            } else {
+                files.push((archive_path(input, &path)?, path, metadata.len()));
+            }
+        }
+        if empty && dir != input {
+            dirs.push(archive_path(input, &dir)?);
+        }
+    }
+    let files_total = files.len();
+    let bytes_total = files.iter().map(|(_, _, size)| size).sum();
+    let mut writer = ZArchiveWriter::with_options(output, options)?;
+    let mut summary = PackSummary::default();
+    for dir in dirs {
+        writer.make_dir(&dir, true)?;
+    }
+    for (archive_path, path, _) in files {
+        writer.start_file(&archive_path)?;
+        summary.bytes += std::io::copy(&mut std::fs::File::open(&path)?, &mut writer)?;
+        summary.files += 1;
+        callback(PackProgress {
+            files_done: summary.files,
+            files_total,
+            bytes_done: summary.bytes,
+            bytes_total,
+            current_file: &archive_path,
+        });
+    }
+    writer.finalize()?;
+    Ok(summary)
+}
+
+/// Get the path of a file in the archive being packed from `root`, using `/`
+/// as the separator.
+pub(crate) fn archive_path(root: &Path, path: &Path) -> Result<String> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let segments = relative
+        .components()
+        .map(|c| {
+            c.as_os_str()
+                .to_str()
+                .ok_or_else(|| ZArchiveError::InvalidFilePath(path.to_string_lossy().to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(segments.join("/"))
+}
 
 pub fn pack(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
     let input = input.as_ref();
@@ -11,7 +472,7 @@ pub fn pack(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
         )));
     }
     if output.exists() {
-        std::fs::remove_file(&output)?;
+        std::fs::remove_file(output)?;
     } else if !output.parent().unwrap().exists() {
         std::fs::create_dir_all(output.parent().unwrap())?;
     }
@@ -31,6 +492,16 @@ mod ffi {
         include!("zarchive/include/zarchive/zarchivewriter.h");
 
         fn Pack(inputPath: &str, outputPath: &str) -> Result<()>;
+
+        type StreamingWriter;
+        fn NewStreamingWriter(outputPath: &str) -> Result<UniquePtr<StreamingWriter>>;
+        fn StartNewFile(self: Pin<&mut StreamingWriter>, path: &str) -> Result<()>;
+        fn AppendData(self: Pin<&mut StreamingWriter>, data: &[u8]) -> Result<()>;
+        fn MakeDir(self: Pin<&mut StreamingWriter>, path: &str, recursive: bool) -> Result<()>;
+        fn SetCompressionLevel(self: Pin<&mut StreamingWriter>, level: i32) -> Result<()>;
+        fn SetCompressionEnabled(self: Pin<&mut StreamingWriter>, enabled: bool) -> Result<()>;
+        fn Finalize(self: Pin<&mut StreamingWriter>) -> Result<()>;
+        fn Reset(self: Pin<&mut StreamingWriter>, outputPath: &str) -> Result<()>;
     }
 }
 
@@ -46,4 +517,322 @@ mod tests {
         let archive2 = crate::reader::ZArchiveReader::open(temp_file.path()).unwrap();
         assert_eq!(archive.get_files().unwrap(), archive2.get_files().unwrap());
     }
+
+    #[test]
+    fn pack_with_progress() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = crate::reader::ZArchiveReader::open("test/crafting.zar").unwrap();
+        archive
+            .extract_filtered(temp_dir.path(), |path, _| {
+                path.starts_with("content/Model/")
+            })
+            .unwrap();
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut reports = vec![];
+        let summary = super::pack_with_progress(
+            &temp_dir,
+            temp_file.path(),
+            Default::default(),
+            |progress| {
+                reports.push((progress.files_done, progress.bytes_done));
+                assert_eq!(progress.files_total, 24);
+                assert!(progress.current_file.starts_with("content/Model/"));
+            },
+        )
+        .unwrap();
+        assert_eq!(reports.len(), 24);
+        assert_eq!(reports.last(), Some(&(summary.files, summary.bytes)));
+        let archive2 = crate::reader::ZArchiveReader::open(temp_file.path()).unwrap();
+        assert_eq!(archive2.file_count_under("content/Model").unwrap(), 24);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pack_with_progress_follows_symlinks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outside = temp_dir.path().join("outside");
+        std::fs::create_dir_all(outside.join("dir")).unwrap();
+        std::fs::write(outside.join("file.txt"), b"file").unwrap();
+        std::fs::write(outside.join("dir/nested.txt"), b"nested").unwrap();
+        let input = temp_dir.path().join("input");
+        std::fs::create_dir(&input).unwrap();
+        std::os::unix::fs::symlink(outside.join("file.txt"), input.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.join("dir"), input.join("linked")).unwrap();
+        let output = temp_dir.path().join("out.zar");
+        let summary =
+            super::pack_with_progress(&input, &output, Default::default(), |_| ()).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, 10);
+        let archive = crate::reader::ZArchiveReader::open(&output).unwrap();
+        assert_eq!(archive.read_file("link.txt").unwrap(), b"file");
+        assert_eq!(archive.read_file("linked/nested.txt").unwrap(), b"nested");
+    }
+
+    #[test]
+    fn recompress() {
+        use super::{PackOptions, ZArchiveWriter};
+        use crate::reader::ZArchiveReader;
+
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source.zar");
+        let mut writer = ZArchiveWriter::new(&source).unwrap();
+        writer.make_dir("empty", false).unwrap();
+        writer.start_file("rules.txt").unwrap();
+        writer
+            .append_data(&archive.read_file("rules.txt").unwrap())
+            .unwrap();
+        writer.start_file("Pack/Bootup.pack").unwrap();
+        writer
+            .append_data(
+                &archive
+                    .read_from_file("content/Pack/Bootup.pack", 0, 1024 * 1024)
+                    .unwrap(),
+            )
+            .unwrap();
+        writer.finalize().unwrap();
+        let source = ZArchiveReader::open(&source).unwrap();
+
+        let fast = temp_dir.path().join("fast.zar");
+        let small = temp_dir.path().join("small.zar");
+        super::recompress(
+            &source,
+            &fast,
+            PackOptions {
+                compression_level: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let summary = super::recompress(
+            &source,
+            &small,
+            PackOptions {
+                compression_level: 19,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, 300 + 1024 * 1024);
+        assert!(std::fs::metadata(&small).unwrap().len() < std::fs::metadata(&fast).unwrap().len());
+        let small = ZArchiveReader::open(&small).unwrap();
+        assert_eq!(small.get_files().unwrap(), source.get_files().unwrap());
+        assert_eq!(small.empty_dirs().unwrap(), vec!["empty".to_owned()]);
+        assert_eq!(
+            small.read_file("Pack/Bootup.pack"),
+            source.read_file("Pack/Bootup.pack")
+        );
+    }
+
+    #[test]
+    fn compress_predicate() {
+        use super::{PackOptions, ZArchiveWriter};
+        use crate::reader::ZArchiveReader;
+
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source.zar");
+        let mut writer = ZArchiveWriter::new(&source).unwrap();
+        writer.start_file("Pack/Bootup.pack").unwrap();
+        writer
+            .append_data(
+                &archive
+                    .read_from_file("content/Pack/Bootup.pack", 0, 256 * 1024)
+                    .unwrap(),
+            )
+            .unwrap();
+        writer.finalize().unwrap();
+        let source = ZArchiveReader::open(&source).unwrap();
+        assert!(source.is_compressed("Pack/Bootup.pack").unwrap());
+
+        let raw = temp_dir.path().join("raw.zar");
+        super::recompress(
+            &source,
+            &raw,
+            PackOptions {
+                compress_predicate: Some(Box::new(|path, data| {
+                    !path.ends_with(".pack") && !data.starts_with(b"SARC")
+                })),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(std::fs::metadata(&raw).unwrap().len() > 256 * 1024);
+        let raw = ZArchiveReader::open(&raw).unwrap();
+        assert!(!raw.is_compressed("Pack/Bootup.pack").unwrap());
+        assert_eq!(
+            raw.read_file("Pack/Bootup.pack"),
+            source.read_file("Pack/Bootup.pack")
+        );
+    }
+
+    #[test]
+    fn pack_generated() {
+        use crate::{reader::ZArchiveReader, ZArchiveError};
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("generated.zar");
+        let summary = super::pack_generated(
+            &["a.txt", "dir/b.txt", "dir/sub/c.txt"],
+            |path| Ok(path.repeat(3).into_bytes()),
+            &output,
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.bytes, (5 + 9 + 13) * 3);
+        let archive = ZArchiveReader::open(&output).unwrap();
+        assert_eq!(
+            archive.read_file("dir/sub/c.txt").unwrap(),
+            b"dir/sub/c.txtdir/sub/c.txtdir/sub/c.txt"
+        );
+        let err = super::pack_generated(
+            &["a.txt"],
+            |path| Err(ZArchiveError::MissingFile(path.to_owned())),
+            temp_dir.path().join("failed.zar"),
+            Default::default(),
+        );
+        assert!(matches!(err, Err(ZArchiveError::MissingFile(_))));
+    }
+
+    #[test]
+    fn pack_generated_compress_predicate() {
+        use super::PackOptions;
+        use crate::reader::ZArchiveReader;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("generated.zar");
+        super::pack_generated(
+            &["raw.bin", "packed.txt"],
+            |_| Ok(vec![b'a'; 256 * 1024]),
+            &output,
+            PackOptions {
+                compress_predicate: Some(Box::new(|path, data| {
+                    assert_eq!(data.len(), 256 * 1024);
+                    !path.ends_with(".bin")
+                })),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let archive = ZArchiveReader::open(&output).unwrap();
+        assert!(!archive.is_compressed("raw.bin").unwrap());
+        assert!(archive.is_compressed("packed.txt").unwrap());
+        assert_eq!(
+            archive.read_file("raw.bin").unwrap(),
+            vec![b'a'; 256 * 1024]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        use super::ZArchiveWriter;
+        use crate::reader::ZArchiveReader;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut writer = ZArchiveWriter::new(temp_dir.path().join("0.zar")).unwrap();
+        for i in 0..3 {
+            if i > 0 {
+                writer
+                    .reset(temp_dir.path().join(format!("{i}.zar")))
+                    .unwrap();
+            }
+            writer.start_file(&format!("mod{i}/data.txt")).unwrap();
+            writer.append_data(i.to_string().as_bytes()).unwrap();
+        }
+        writer.finalize().unwrap();
+        for i in 0..3 {
+            let archive = ZArchiveReader::open(temp_dir.path().join(format!("{i}.zar"))).unwrap();
+            assert_eq!(
+                archive.get_files().unwrap(),
+                vec![format!("mod{i}/data.txt")]
+            );
+            assert_eq!(
+                archive.read_file(format!("mod{i}/data.txt")).unwrap(),
+                i.to_string().as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        use super::ZArchiveWriter;
+        use crate::reader::ZArchiveReader;
+        use std::collections::HashMap;
+
+        let archive = ZArchiveReader::open("test/crafting.zar").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let rules = temp_dir.path().join("rules.txt");
+        std::fs::write(&rules, b"patched").unwrap();
+        let extra = temp_dir.path().join("extra.bin");
+        std::fs::write(&extra, b"new").unwrap();
+        let overrides = HashMap::from([
+            ("RULES.txt".to_owned(), rules),
+            ("added/extra.bin".to_owned(), extra),
+        ]);
+        let output = temp_dir.path().join("patched.zar");
+        let summary =
+            ZArchiveWriter::from_reader_with_overrides(&output, &archive, &overrides).unwrap();
+        let files = archive.get_files().unwrap();
+        assert_eq!(summary.files, files.len() + 1);
+        let patched = ZArchiveReader::open(&output).unwrap();
+        assert_eq!(patched.read_file("rules.txt").unwrap(), b"patched");
+        assert_eq!(patched.read_file("added/extra.bin").unwrap(), b"new");
+        assert_eq!(
+            patched.read_file("content/Model/Item_Feather.sbfres"),
+            archive.read_file("content/Model/Item_Feather.sbfres")
+        );
+    }
+
+    #[test]
+    fn pack_stream() {
+        use std::io::Read;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let entries = vec![
+            Ok(("a.txt".to_owned(), Box::new(&b"hello"[..]) as Box<dyn Read>)),
+            Ok((
+                "sub/dir/b.bin".to_owned(),
+                Box::new(std::io::Cursor::new(vec![7u8; 100_000])) as Box<dyn Read>,
+            )),
+        ];
+        super::pack_stream(entries, temp_file.path(), Default::default()).unwrap();
+        let archive = crate::reader::ZArchiveReader::open(temp_file.path()).unwrap();
+        assert_eq!(archive.read_file("a.txt").unwrap(), b"hello");
+        assert_eq!(
+            archive.read_file("sub/dir/b.bin").unwrap(),
+            vec![7u8; 100_000]
+        );
+    }
+
+    #[test]
+    fn merge() {
+        use super::{ConflictPolicy, ZArchiveWriter};
+        use crate::reader::ZArchiveReader;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let first = temp_dir.path().join("first.zar");
+        let second = temp_dir.path().join("second.zar");
+        let merged = temp_dir.path().join("merged.zar");
+        let mut writer = ZArchiveWriter::new(&first).unwrap();
+        writer.start_file("a.txt").unwrap();
+        writer.append_data(b"first").unwrap();
+        writer.finalize().unwrap();
+        let mut writer = ZArchiveWriter::new(&second).unwrap();
+        writer.start_file("A.txt").unwrap();
+        writer.append_data(b"second").unwrap();
+        writer.start_file("dir/b.txt").unwrap();
+        writer.append_data(b"b").unwrap();
+        writer.finalize().unwrap();
+
+        let sources = [
+            ZArchiveReader::open(&first).unwrap(),
+            ZArchiveReader::open(&second).unwrap(),
+        ];
+        let summary = ZArchiveWriter::merge(&merged, &sources, ConflictPolicy::LastWins).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, 7);
+        let archive = ZArchiveReader::open(&merged).unwrap();
+        assert_eq!(archive.read_file("a.txt").unwrap(), b"second");
+        assert_eq!(archive.read_file("dir/b.txt").unwrap(), b"b");
+        assert!(ZArchiveWriter::merge(&merged, &sources, ConflictPolicy::Error).is_err());
+    }
 }
@@ -1,6 +1,10 @@
 #include "zarchive/src/reader.rs.h"
 
+#include <cstring>
 #include <fstream>
+#include <stdexcept>
+#include <string>
+#include <thread>
 
 #include <zstd.h>
 #include <cassert>
@@ -18,6 +22,19 @@ static bool _ifstream_readBytes(std::ifstream& file, uint64_t offset, void* buff
 	return file.gcount() == size;
 }
 
+static bool _memory_readBytes(const rust::Vec<uint8_t>& data, uint64_t offset, void* buffer, uint32_t size)
+{
+	if (offset > data.size() || size > data.size() - offset)
+		return false;
+	memcpy(buffer, data.data() + offset, size);
+	return true;
+}
+
+static bool _source_readBytes(ReadSource& source, uint64_t offset, void* buffer, uint32_t size)
+{
+	return source.read_at(offset, rust::Slice<uint8_t>((uint8_t*)buffer, size));
+}
+
 static uint64_t _getValidElementCount(uint64_t size, uint64_t elementSize)
 {
 	if ((size % elementSize) != 0)
@@ -25,18 +42,61 @@ static uint64_t _getValidElementCount(uint64_t size, uint64_t elementSize)
 	return size / elementSize;
 }
 
-std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromFile(const std::filesystem::path& path)
+std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromFile(const std::filesystem::path& path, uint64_t baseOffset, uint64_t windowSize)
 {
 	std::ifstream file;
 	file.open(path, std::ios_base::in | std::ios_base::binary);
 	if (!file.is_open())
 		return nullptr;
 	uint64_t fileSize = _ifstream_getFileSize(file);
+	if (baseOffset > fileSize)
+		return nullptr;
+	// from here on fileSize is the size of the window, all offsets are relative to baseOffset
+	fileSize = std::min(windowSize, fileSize - baseOffset);
+	auto reader = Load(fileSize, [&](uint64_t offset, void* buffer, uint32_t size) {
+		return _ifstream_readBytes(file, baseOffset + offset, buffer, size);
+	});
+	if (!reader)
+		return nullptr;
+	reader->m_file = std::move(file);
+	reader->m_path = path;
+	reader->m_baseOffset = baseOffset;
+	reader->m_windowSize = windowSize;
+	return reader;
+}
+
+std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromMemory(rust::Vec<uint8_t>&& data)
+{
+	auto reader = Load(data.size(), [&](uint64_t offset, void* buffer, uint32_t size) {
+		return _memory_readBytes(data, offset, buffer, size);
+	});
+	if (!reader)
+		return nullptr;
+	reader->m_memory = std::move(data);
+	reader->m_inMemory = true;
+	return reader;
+}
+
+std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromSource(rust::Box<ReadSource>&& source, uint64_t archiveSize)
+{
+	auto reader = Load(archiveSize, [&](uint64_t offset, void* buffer, uint32_t size) {
+		return _source_readBytes(*source, offset, buffer, size);
+	});
+	if (!reader)
+		return nullptr;
+	reader->m_source.emplace(std::move(source));
+	reader->m_sourceSize = archiveSize;
+	return reader;
+}
+
+// parses the footer and tables of an archive of the given size, reading through readBytes
+std::unique_ptr<ZArchiveReader> ZArchiveReader::Load(uint64_t fileSize, const std::function<bool(uint64_t offset, void* buffer, uint32_t size)>& readBytes)
+{
 	if (fileSize <= sizeof(_ZARCHIVE::Footer))
 		return nullptr;
 	// read footer
 	_ZARCHIVE::Footer footer;
-	if (!_ifstream_readBytes(file, fileSize - sizeof(_ZARCHIVE::Footer), &footer, sizeof(_ZARCHIVE::Footer)))
+	if (!readBytes(fileSize - sizeof(_ZARCHIVE::Footer), &footer, sizeof(_ZARCHIVE::Footer)))
 		return nullptr;
 	_ZARCHIVE::Footer::Deserialize(&footer, &footer);
 	// validate footer
@@ -62,18 +122,18 @@ std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromFile(const std::filesyst
 	// read offset records
 	std::vector<_ZARCHIVE::CompressionOffsetRecord> offsetRecords;
 	offsetRecords.resize(_getValidElementCount(footer.sectionOffsetRecords.size, sizeof(_ZARCHIVE::CompressionOffsetRecord)));
-	if (offsetRecords.empty() || !_ifstream_readBytes(file, footer.sectionOffsetRecords.offset, offsetRecords.data(), (uint32_t)(offsetRecords.size() * sizeof(_ZARCHIVE::CompressionOffsetRecord))))
+	if (offsetRecords.empty() || !readBytes(footer.sectionOffsetRecords.offset, offsetRecords.data(), (uint32_t)(offsetRecords.size() * sizeof(_ZARCHIVE::CompressionOffsetRecord))))
 		return nullptr;
 	_ZARCHIVE::CompressionOffsetRecord::Deserialize(offsetRecords.data(), offsetRecords.size(), offsetRecords.data());
 	// read name table
 	std::vector<uint8_t> nameTable;
 	nameTable.resize(footer.sectionNames.size);
-	if (!_ifstream_readBytes(file, footer.sectionNames.offset, nameTable.data(), (uint32_t)(nameTable.size() * sizeof(uint8_t))))
+	if (!readBytes(footer.sectionNames.offset, nameTable.data(), (uint32_t)(nameTable.size() * sizeof(uint8_t))))
 		return nullptr;
 	// read file tree
 	std::vector<_ZARCHIVE::FileDirectoryEntry> fileTree;
 	fileTree.resize(_getValidElementCount(footer.sectionFileTree.size, sizeof(_ZARCHIVE::FileDirectoryEntry)));
-	if (fileTree.empty() || !_ifstream_readBytes(file, footer.sectionFileTree.offset, fileTree.data(), (uint32_t)(fileTree.size() * sizeof(_ZARCHIVE::FileDirectoryEntry))))
+	if (fileTree.empty() || !readBytes(footer.sectionFileTree.offset, fileTree.data(), (uint32_t)(fileTree.size() * sizeof(_ZARCHIVE::FileDirectoryEntry))))
 		return nullptr;
 	_ZARCHIVE::FileDirectoryEntry::Deserialize(fileTree.data(), fileTree.size(), fileTree.data());
 	// verify file tree
@@ -85,7 +145,9 @@ std::unique_ptr<ZArchiveReader> ZArchiveReader::OpenFromFile(const std::filesyst
 	// read meta data
 	// todo
 
-	return std::make_unique<ZArchiveReader>(std::move(file), std::move(offsetRecords), std::move(nameTable), std::move(fileTree), footer.sectionCompressedData.offset, footer.sectionCompressedData.size);
+	auto reader = std::make_unique<ZArchiveReader>(std::ifstream(), std::move(offsetRecords), std::move(nameTable), std::move(fileTree), footer.sectionCompressedData.offset, footer.sectionCompressedData.size);
+	reader->m_version = footer.version;
+	return reader;
 }
 
 ZArchiveReader::ZArchiveReader(std::ifstream&& file, std::vector<_ZARCHIVE::CompressionOffsetRecord>&& offsetRecords, std::vector<uint8_t>&& nameTable, std::vector<_ZARCHIVE::FileDirectoryEntry>&& fileTree, uint64_t compressedDataOffset, uint64_t compressedDataSize) :
@@ -93,6 +155,11 @@ ZArchiveReader::ZArchiveReader(std::ifstream&& file, std::vector<_ZARCHIVE::Comp
 	m_compressedDataOffset(compressedDataOffset), m_compressedDataSize(compressedDataSize)
 {
 	m_blockCount = (uint64_t)m_offsetRecords.size() * _ZARCHIVE::ENTRIES_PER_OFFSETRECORD;
+	for (auto& entry : m_fileTree)
+	{
+		if (entry.IsFile())
+			m_fileCount++;
+	}
 	m_blockDecompressionBuffer.resize(_ZARCHIVE::COMPRESSED_BLOCK_SIZE);
 	// init cache
 	uint64_t cacheSize = 1024 * 1024 * 4; // 4MiB
@@ -129,33 +196,47 @@ ZArchiveNodeHandle ZArchiveReader::LookUp(rust::Str path, bool allowFile, bool a
 		std::string_view pathNodeName;
 		if (!_ZARCHIVE::GetNextPathNode(pathParser, pathNodeName))
 			return (ZArchiveNodeHandle)currentNode; // end of path reached
-		_ZARCHIVE::FileDirectoryEntry& entry = m_fileTree.at(currentNode);
-		if (entry.IsFile())
-			return ZARCHIVE_INVALID_NODE; // trying to iterate a file
-		// linear scan
-		// todo - we could accelerate this if we use binary search
-		uint32_t currentIndex = entry.directoryRecord.nodeStartIndex;
-		uint32_t endIndex = entry.directoryRecord.nodeStartIndex + entry.directoryRecord.count;
-		_ZARCHIVE::FileDirectoryEntry* match = nullptr;
-		while (currentIndex < endIndex)
-		{
-			_ZARCHIVE::FileDirectoryEntry& it = m_fileTree.at(currentIndex);
-			std::string_view itName = GetName(m_nameTable, it.GetNameOffset());
-			if (_ZARCHIVE::CompareNodeNameBool(pathNodeName, itName))
-			{
-				match = &it;
-				break;
-			}
-			currentIndex++;
-			continue;
-		}
-		if (!match)
-			return ZARCHIVE_INVALID_NODE; // path not found
-		currentNode = (uint32_t)(match - m_fileTree.data());
+		currentNode = FindChild(currentNode, pathNodeName);
+		if (currentNode == ZARCHIVE_INVALID_NODE)
+			return ZARCHIVE_INVALID_NODE;
 	}
 	return ZARCHIVE_INVALID_NODE;
 }
 
+ZArchiveNodeHandle ZArchiveReader::LookUpSegments(rust::Slice<const rust::Str> segments)
+{
+	uint32_t currentNode = 0;
+	for (const rust::Str& segment : segments)
+	{
+		if (segment.empty())
+			continue;
+		currentNode = FindChild(currentNode, std::string_view(segment.data(), segment.size()));
+		if (currentNode == ZARCHIVE_INVALID_NODE)
+			return ZARCHIVE_INVALID_NODE;
+	}
+	return (ZArchiveNodeHandle)currentNode;
+}
+
+uint32_t ZArchiveReader::FindChild(uint32_t directoryNode, std::string_view name)
+{
+	_ZARCHIVE::FileDirectoryEntry& entry = m_fileTree.at(directoryNode);
+	if (entry.IsFile())
+		return ZARCHIVE_INVALID_NODE; // trying to iterate a file
+	// linear scan
+	// todo - we could accelerate this if we use binary search
+	uint32_t currentIndex = entry.directoryRecord.nodeStartIndex;
+	uint32_t endIndex = entry.directoryRecord.nodeStartIndex + entry.directoryRecord.count;
+	while (currentIndex < endIndex)
+	{
+		_ZARCHIVE::FileDirectoryEntry& it = m_fileTree.at(currentIndex);
+		std::string_view itName = GetName(m_nameTable, it.GetNameOffset());
+		if (_ZARCHIVE::CompareNodeNameBool(name, itName))
+			return currentIndex;
+		currentIndex++;
+	}
+	return ZARCHIVE_INVALID_NODE; // path not found
+}
+
 bool ZArchiveReader::IsDirectory(ZArchiveNodeHandle nodeHandle) const
 {
 	if (nodeHandle >= m_fileTree.size())
@@ -213,6 +294,37 @@ uint64_t ZArchiveReader::GetFileSize(ZArchiveNodeHandle nodeHandle)
 	return file.GetFileSize();
 }
 
+uint64_t ZArchiveReader::GetFileDataOffset(ZArchiveNodeHandle nodeHandle) const
+{
+	if (nodeHandle >= m_fileTree.size())
+		return 0;
+	auto& file = m_fileTree.at(nodeHandle);
+	if (!file.IsFile())
+		return 0;
+	return file.GetFileOffset();
+}
+
+uint64_t ZArchiveReader::GetBlockPhysicalOffset(uint64_t blockIndex) const
+{
+	uint64_t recordIndex = blockIndex / _ZARCHIVE::ENTRIES_PER_OFFSETRECORD;
+	uint32_t recordSubIndex = (uint32_t)(blockIndex % _ZARCHIVE::ENTRIES_PER_OFFSETRECORD);
+	if (recordIndex >= m_offsetRecords.size())
+		throw std::out_of_range("Missing offset record for block " + std::to_string(blockIndex));
+	auto& record = m_offsetRecords[recordIndex];
+	uint64_t offset = record.baseOffset;
+	for (uint32_t i = 0; i < recordSubIndex; i++)
+		offset += (uint64_t)record.size[i] + 1;
+	return m_compressedDataOffset + offset;
+}
+
+uint32_t ZArchiveReader::GetBlockCompressedSize(uint64_t blockIndex) const
+{
+	uint64_t recordIndex = blockIndex / _ZARCHIVE::ENTRIES_PER_OFFSETRECORD;
+	if (recordIndex >= m_offsetRecords.size())
+		throw std::out_of_range("Missing offset record for block " + std::to_string(blockIndex));
+	return (uint32_t)m_offsetRecords[recordIndex].size[blockIndex % _ZARCHIVE::ENTRIES_PER_OFFSETRECORD] + 1;
+}
+
 uint64_t ZArchiveReader::ReadFromFile(ZArchiveNodeHandle nodeHandle, uint64_t offset, uint64_t length, uint8_t* buffer)
 {
 	if (nodeHandle >= m_fileTree.size())
@@ -237,15 +349,24 @@ uint64_t ZArchiveReader::ReadFromFile(ZArchiveNodeHandle nodeHandle, uint64_t of
 		uint32_t stepSize = std::min(remainingBytes, _ZARCHIVE::COMPRESSED_BLOCK_SIZE - blockOffset);
 		CacheBlock* block = GetCachedBlock(blockIdx);
 		if (!block)
-			return 0;
+		{
+			ReleaseFile();
+			throw std::runtime_error(m_lastError);
+		}
 		std::memcpy(bufferU8, block->data + blockOffset, stepSize);
 		rawReadOffset += stepSize;
 		remainingBytes -= stepSize;
 		bufferU8 += stepSize;
 	}
+	ReleaseFile();
 	return bytesToRead;
 }
 
+ReadErrorKind ZArchiveReader::GetLastErrorKind() const
+{
+	return m_lastErrorKind;
+}
+
 ZArchiveReader::CacheBlock* ZArchiveReader::GetCachedBlock(uint64_t blockIndex)
 {
 	auto it = m_blockLookup.find(blockIndex);
@@ -255,7 +376,11 @@ ZArchiveReader::CacheBlock* ZArchiveReader::GetCachedBlock(uint64_t blockIndex)
 		return it->second;
 	}
 	if (blockIndex >= m_blockCount)
+	{
+		m_lastError = "Block index out of range: " + std::to_string(blockIndex);
+		m_lastErrorKind = ReadErrorKind::Corrupt;
 		return nullptr;
+	}
 	// not in cache
 	CacheBlock* newBlock = RecycleLRUBlock(blockIndex);
 	if (!LoadBlock(newBlock))
@@ -320,7 +445,11 @@ bool ZArchiveReader::LoadBlock(CacheBlock* block)
 	uint32_t recordIndex = (uint32_t)(block->blockIndex / _ZARCHIVE::ENTRIES_PER_OFFSETRECORD);
 	uint32_t recordSubIndex = (uint32_t)(block->blockIndex % _ZARCHIVE::ENTRIES_PER_OFFSETRECORD);
 	if (recordIndex >= m_offsetRecords.size())
+	{
+		m_lastError = "Missing offset record for block " + std::to_string(block->blockIndex);
+		m_lastErrorKind = ReadErrorKind::Corrupt;
 		return false;
+	}
 	// determine offset and size of compressed block
 	auto& record = m_offsetRecords[recordIndex];
 	uint64_t offset = record.baseOffset;
@@ -331,19 +460,36 @@ bool ZArchiveReader::LoadBlock(CacheBlock* block)
 	}
 	uint32_t compressedSize = (uint32_t)record.size[recordSubIndex] + 1;
 	// load file data
+	m_lastError = "Failed to read block " + std::to_string(block->blockIndex) + " from archive";
+	m_lastErrorKind = ReadErrorKind::Io;
 	if ((offset + compressedSize) > m_compressedDataSize)
+	{
+		m_lastErrorKind = ReadErrorKind::Corrupt;
 		return false;
+	}
 	offset += m_compressedDataOffset;
 	if (compressedSize == _ZARCHIVE::COMPRESSED_BLOCK_SIZE)
 	{
 		// uncompressed block, read directly into cached block
-		return _ifstream_readBytes(m_file, offset, block->data, compressedSize);
+		return ReadWithRetry(offset, block->data, compressedSize);
 	}
-	if (!_ifstream_readBytes(m_file, offset, m_blockDecompressionBuffer.data(), compressedSize))
+	if (!ReadWithRetry(offset, m_blockDecompressionBuffer.data(), compressedSize))
 		return false;
 	// decompress
 	size_t outputSize = ZSTD_decompress(block->data, _ZARCHIVE::COMPRESSED_BLOCK_SIZE, m_blockDecompressionBuffer.data(), compressedSize);
-	return outputSize == _ZARCHIVE::COMPRESSED_BLOCK_SIZE;
+	if (ZSTD_isError(outputSize))
+	{
+		m_lastError = "Decompression failed for block " + std::to_string(block->blockIndex) + ": " + ZSTD_getErrorName(outputSize);
+		m_lastErrorKind = ReadErrorKind::Decompression;
+		return false;
+	}
+	if (outputSize != _ZARCHIVE::COMPRESSED_BLOCK_SIZE)
+	{
+		m_lastError = "Decompression failed for block " + std::to_string(block->blockIndex) + ": unexpected size " + std::to_string(outputSize);
+		m_lastErrorKind = ReadErrorKind::Decompression;
+		return false;
+	}
+	return true;
 }
 
 // returns empty view on failure
@@ -369,7 +515,182 @@ std::string_view ZArchiveReader::GetName(const std::vector<uint8_t>& nameTable,
 	return std::basic_string_view<char>((char*)nameTable.data() + nameOffset, nameLength);
 }
 
+uint32_t ZArchiveReader::GetVersion() const
+{
+	// the footer stores the version as a magic word, decode it to its number
+	if (m_version == _ZARCHIVE::Footer::kVersion1)
+		return 1;
+	return 0;
+}
+
+uint64_t ZArchiveReader::GetBlockCount() const
+{
+	return m_blockCount;
+}
+
+uint64_t ZArchiveReader::GetOffsetRecordCount() const
+{
+	return m_offsetRecords.size();
+}
+
+uint64_t ZArchiveReader::GetCompressedDataSize() const
+{
+	return m_compressedDataSize;
+}
+
+uint64_t ZArchiveReader::GetFileCount() const
+{
+	return m_fileCount;
+}
+
+uint64_t ZArchiveReader::GetArchiveSize()
+{
+	std::unique_lock<std::mutex> _lock(m_accessMutex);
+	if (!EnsureFileOpen())
+		throw std::runtime_error("Failed to reopen archive");
+	uint64_t archiveSize = GetWindowSize();
+	ReleaseFile();
+	return archiveSize;
+}
+
+uint64_t ZArchiveReader::ReadArchiveBytes(uint64_t offset, uint64_t length, uint8_t* buffer)
+{
+	std::unique_lock<std::mutex> _lock(m_accessMutex);
+	if (!EnsureFileOpen())
+		throw std::runtime_error("Failed to reopen archive");
+	uint64_t archiveSize = GetWindowSize();
+	if (offset >= archiveSize)
+	{
+		ReleaseFile();
+		return 0;
+	}
+	length = std::min<uint64_t>(length, archiveSize - offset);
+	if (length > (uint64_t)0xFFFFFFFF)
+		length = 0xFFFFFFFF;
+	bool success = ReadWithRetry(offset, buffer, (uint32_t)length);
+	ReleaseFile();
+	if (!success)
+		throw std::runtime_error("Failed to read from archive at offset " + std::to_string(offset));
+	return length;
+}
+
+void ZArchiveReader::SetKeepOpen(bool keepOpen)
+{
+	std::unique_lock<std::mutex> _lock(m_accessMutex);
+	m_keepOpen = keepOpen;
+	ReleaseFile();
+}
+
+std::unique_ptr<ZArchiveReader> ZArchiveReader::Reopen()
+{
+	std::unique_lock<std::mutex> _lock(m_accessMutex);
+	if (m_inMemory || m_source)
+		throw std::runtime_error("Archive was not opened from a file and cannot be reopened");
+	auto reader = OpenFromFile(m_path, m_baseOffset, m_windowSize);
+	if (!reader)
+		throw std::runtime_error("Failed to reopen archive");
+	reader->m_keepOpen = m_keepOpen;
+	reader->m_retryAttempts = m_retryAttempts;
+	reader->m_retryBackoff = m_retryBackoff;
+	reader->ReleaseFile();
+	return reader;
+}
+
+void ZArchiveReader::SetRetryPolicy(uint32_t attempts, uint64_t backoffUs)
+{
+	std::unique_lock<std::mutex> _lock(m_accessMutex);
+	m_retryAttempts = attempts;
+	m_retryBackoff = std::chrono::microseconds(backoffUs);
+}
+
+uint64_t ZArchiveReader::GetWindowSize()
+{
+	if (m_inMemory)
+		return m_memory.size();
+	if (m_source)
+		return m_sourceSize;
+	uint64_t fileSize = _ifstream_getFileSize(m_file);
+	if (fileSize < m_baseOffset)
+		return 0;
+	return std::min(m_windowSize, fileSize - m_baseOffset);
+}
+
+bool ZArchiveReader::EnsureFileOpen()
+{
+	if (m_inMemory || m_source || m_file.is_open())
+		return true;
+	m_file.clear();
+	m_file.open(m_path, std::ios_base::in | std::ios_base::binary);
+	return m_file.is_open();
+}
+
+// only I/O failures are retried, the caller checks the data itself (e.g. decompression) afterwards
+bool ZArchiveReader::ReadWithRetry(uint64_t offset, void* buffer, uint32_t size)
+{
+	std::string readError = "Failed to read " + std::to_string(size) + " bytes at offset " + std::to_string(offset) + " from archive";
+	if (m_inMemory || m_source)
+	{
+		bool success = m_inMemory ? _memory_readBytes(m_memory, offset, buffer, size) : _source_readBytes(**m_source, offset, buffer, size);
+		if (!success)
+		{
+			m_lastError = readError;
+			m_lastErrorKind = ReadErrorKind::Io;
+		}
+		return success;
+	}
+	for (uint32_t attempt = 0;; attempt++)
+	{
+		if (!EnsureFileOpen())
+			m_lastError = "Failed to reopen archive";
+		else if (_ifstream_readBytes(m_file, m_baseOffset + offset, buffer, size))
+			return true;
+		else
+			m_lastError = readError;
+		m_lastErrorKind = ReadErrorKind::Io;
+		if (attempt > 0)
+			m_lastError += " after " + std::to_string(attempt + 1) + " attempts";
+		if (attempt >= m_retryAttempts)
+			return false;
+		// a failed stream stays failed, so start the next attempt from a fresh open
+		m_file.close();
+		std::this_thread::sleep_for(m_retryBackoff * (1ull << std::min<uint32_t>(attempt, 16)));
+	}
+}
+
+void ZArchiveReader::ReleaseFile()
+{
+	if (!m_keepOpen && m_file.is_open())
+		m_file.close();
+}
+
 std::unique_ptr<ZArchiveReader> OpenFromFile(const rust::Str path)
 {
-	return ZArchiveReader::OpenFromFile(std::filesystem::path(std::string_view(path.data(), path.size())));
+	auto reader = ZArchiveReader::OpenFromFile(_ZARCHIVE::PathFromUtf8(path.data(), path.size()));
+	if (!reader)
+		throw std::runtime_error("Failed to open archive or not a valid archive");
+	return reader;
+}
+
+std::unique_ptr<ZArchiveReader> OpenFromMemory(rust::Vec<uint8_t> data)
+{
+	auto reader = ZArchiveReader::OpenFromMemory(std::move(data));
+	if (!reader)
+		throw std::runtime_error("Not a valid archive");
+	return reader;
+}
+
+std::unique_ptr<ZArchiveReader> OpenFromSource(rust::Box<ReadSource> source, uint64_t size)
+{
+	auto reader = ZArchiveReader::OpenFromSource(std::move(source), size);
+	if (!reader)
+		throw std::runtime_error("Not a valid archive");
+	return reader;
+}
+
+std::unique_ptr<ZArchiveReader> OpenFromFileRange(const rust::Str path, uint64_t offset, uint64_t size)
+{
+	auto reader = ZArchiveReader::OpenFromFile(_ZARCHIVE::PathFromUtf8(path.data(), path.size()), offset, size);
+	if (!reader)
+		throw std::runtime_error("No archive at offset " + std::to_string(offset) + " with size " + std::to_string(size));
+	return reader;
 }
@@ -6,6 +6,7 @@
 #include <string>
 #include <string_view>
 #include <queue>
+#include <stdexcept>
 
 #include <zstd.h>
 
@@ -141,12 +142,16 @@ void ZArchiveWriter::StoreBlock(const uint8_t* uncompressedData)
 {
 	// compress and store
 	uint64_t compressedWriteOffset = GetCurrentOutputOffset();
-	m_compressionBuffer.resize(ZSTD_compressBound(_ZARCHIVE::COMPRESSED_BLOCK_SIZE));
-	size_t outputSize = ZSTD_compress(m_compressionBuffer.data(), m_compressionBuffer.size(), uncompressedData, _ZARCHIVE::COMPRESSED_BLOCK_SIZE, 6);
-	assert(outputSize >= 0);
+	size_t outputSize = _ZARCHIVE::COMPRESSED_BLOCK_SIZE;
+	if (m_compressionEnabled)
+	{
+		m_compressionBuffer.resize(ZSTD_compressBound(_ZARCHIVE::COMPRESSED_BLOCK_SIZE));
+		outputSize = ZSTD_compress(m_compressionBuffer.data(), m_compressionBuffer.size(), uncompressedData, _ZARCHIVE::COMPRESSED_BLOCK_SIZE, m_compressionLevel);
+		assert(outputSize >= 0);
+	}
 	if (outputSize >= _ZARCHIVE::COMPRESSED_BLOCK_SIZE)
 	{
-		// store block uncompressed if it is equal or larger than the input after compression
+		// store block uncompressed if compression is disabled or it is equal or larger than the input after compression
 		outputSize = _ZARCHIVE::COMPRESSED_BLOCK_SIZE;
 		OutputData(uncompressedData, _ZARCHIVE::COMPRESSED_BLOCK_SIZE);
 	}
@@ -192,6 +197,16 @@ void ZArchiveWriter::AppendData(const void* data, size_t size)
 	m_currentInputOffset += dataSize;
 }
 
+void ZArchiveWriter::SetCompressionLevel(int32_t level)
+{
+	m_compressionLevel = level;
+}
+
+void ZArchiveWriter::SetCompressionEnabled(bool enabled)
+{
+	m_compressionEnabled = enabled;
+}
+
 void ZArchiveWriter::Finalize()
 {
 	m_currentFileNode = nullptr; // make sure the padding added below doesn't modify the active file
@@ -217,6 +232,37 @@ void ZArchiveWriter::Finalize()
 	WriteFooter();
 }
 
+void ZArchiveWriter::Reset()
+{
+	FreeSubnodes(&m_rootNode);
+	m_rootNode = PathNode();
+	m_currentFileNode = nullptr;
+	m_nodeNames.clear();
+	m_nodeNameOffsets.clear();
+	m_nodeNameLookup.clear();
+	m_footer = {};
+	// clear() keeps the allocations around for the next archive
+	m_currentWriteBuffer.clear();
+	m_compressionOffsetRecord.clear();
+	m_currentCompressedWriteIndex = 0;
+	m_currentInputOffset = 0;
+	m_numWrittenOffsetRecords = 0;
+	if (!m_mainShaCtx)
+		m_mainShaCtx = (struct Sha_256*)malloc(sizeof(struct Sha_256));
+	sha_256_init(m_mainShaCtx, m_integritySha);
+	m_cbNewOutputFile(-1, m_cbCtx);
+}
+
+void ZArchiveWriter::FreeSubnodes(PathNode* node)
+{
+	for (PathNode* subnode : node->subnodes)
+	{
+		FreeSubnodes(subnode);
+		delete subnode;
+	}
+	node->subnodes.clear();
+}
+
 void ZArchiveWriter::WriteOffsetRecords()
 {
 	m_footer.sectionOffsetRecords.offset = GetCurrentOutputOffset();
@@ -434,3 +480,76 @@ void Pack(rust::Str inputPath, rust::Str outputPath)
 	zWriter.Finalize();
 	return;
 }
+
+//------------------------------------------//
+// Streaming writer for the Rust bindings   //
+// -----------------------------------------//
+
+StreamingWriter::StreamingWriter(const fs::path& outputPath) : m_outputPath(outputPath), m_writer(NewOutputFile, WriteOutputData, this)
+{
+}
+
+void StreamingWriter::NewOutputFile(const int32_t partIndex, void* ctx)
+{
+	StreamingWriter* writer = (StreamingWriter*)ctx;
+	writer->m_outputFile = std::ofstream(writer->m_outputPath, std::ios::binary);
+	if (!writer->m_outputFile.is_open())
+		throw std::runtime_error("Failed to create output file: " + writer->m_outputPath.string());
+}
+
+void StreamingWriter::WriteOutputData(const void* data, size_t length, void* ctx)
+{
+	StreamingWriter* writer = (StreamingWriter*)ctx;
+	writer->m_outputFile.write((const char*)data, length);
+	if (!writer->m_outputFile)
+		throw std::runtime_error("Failed to write to output file: " + writer->m_outputPath.string());
+}
+
+void StreamingWriter::StartNewFile(rust::Str path)
+{
+	std::string filePath(path);
+	if (!m_writer.StartNewFile(filePath.c_str()))
+		throw std::runtime_error("Failed to create archive file " + filePath);
+}
+
+void StreamingWriter::AppendData(rust::Slice<const uint8_t> data)
+{
+	m_writer.AppendData(data.data(), data.size());
+}
+
+void StreamingWriter::MakeDir(rust::Str path, bool recursive)
+{
+	std::string dirPath(path);
+	if (!m_writer.MakeDir(dirPath.c_str(), recursive))
+		throw std::runtime_error("Failed to create directory " + dirPath);
+}
+
+void StreamingWriter::SetCompressionLevel(int32_t level)
+{
+	m_writer.SetCompressionLevel(level);
+}
+
+void StreamingWriter::SetCompressionEnabled(bool enabled)
+{
+	m_writer.SetCompressionEnabled(enabled);
+}
+
+void StreamingWriter::Finalize()
+{
+	m_writer.Finalize();
+	m_outputFile.close();
+	if (m_outputFile.fail())
+		throw std::runtime_error("Failed to finish output file: " + m_outputPath.string());
+}
+
+void StreamingWriter::Reset(rust::Str outputPath)
+{
+	m_outputFile.close();
+	m_outputPath = _ZARCHIVE::PathFromUtf8(outputPath.data(), outputPath.size());
+	m_writer.Reset();
+}
+
+std::unique_ptr<StreamingWriter> NewStreamingWriter(rust::Str outputPath)
+{
+	return std::make_unique<StreamingWriter>(_ZARCHIVE::PathFromUtf8(outputPath.data(), outputPath.size()));
+}
@@ -0,0 +1,45 @@
+//! Kept in its own test binary, since the captured warnings come from a
+//! process-wide logger.
+use std::sync::Mutex;
+use zarchive::ZArchiveWriter;
+
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            WARNINGS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture;
+
+#[test]
+fn drop_without_finalize_warns() {
+    log::set_logger(&CAPTURE).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut writer = ZArchiveWriter::new(temp_dir.path().join("done.zar")).unwrap();
+    writer.start_file("a.txt").unwrap();
+    writer.append_data(b"a").unwrap();
+    writer.finalize().unwrap();
+    assert!(WARNINGS.lock().unwrap().is_empty());
+
+    let mut writer = ZArchiveWriter::new(temp_dir.path().join("dropped.zar")).unwrap();
+    writer.start_file("a.txt").unwrap();
+    writer.append_data(b"a").unwrap();
+    drop(writer);
+    let warnings = WARNINGS.lock().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("without being finalized"));
+}